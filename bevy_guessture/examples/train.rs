@@ -1,14 +1,24 @@
+use bevy::asset::io::AssetSource;
+use bevy::asset::{AssetPath, AsyncReadExt};
+use bevy::input::keyboard::KeyboardInput;
+use bevy::input::ButtonState;
 use bevy::prelude::*;
 use bevy::sprite::MaterialMesh2dBundle;
-use bevy_guessture::{GuessturePlugin, GestureRecord, GestureState, RecordedPath, GestureTemplates};
-use guessture::{Template, find_matching_template_with_defaults};
-use std::fs::File;
-use std::io::Write;
-use std::path::Path;
+use bevy::tasks::IoTaskPool;
+use bevy::window::ReceivedCharacter;
+use bevy_guessture::{
+    GuessturePlugin, GestureProgress, GestureRecord, GestureState, GestureTemplatesSaved,
+    RecordedPath, SaveGestureTemplates,
+};
+use guessture::{Path2D, Template, find_matching_template_with_defaults};
+use std::env;
+use std::error::Error;
+use std::sync::{Arc, Mutex};
 
 #[derive(Copy, Clone)]
 enum RecordType {
     Template,
+    Rerecord(usize),
     Attempt,
 }
 
@@ -17,21 +27,86 @@ struct RecordState {
     state: Option<RecordType>,
 }
 
+/// Editing state for the list of templates being authored.
+#[derive(Resource)]
+struct Library {
+    /// The index of the currently selected template, if any.
+    selected: Option<usize>,
+    /// The most recently entered template name, offered as the default for the next recording.
+    last_name: String,
+    /// The most recently used file path, offered as the default for saving and loading.
+    file_path: String,
+    /// True if the visible template list needs to be rebuilt.
+    dirty: bool,
+}
+
+impl Default for Library {
+    fn default() -> Self {
+        Library {
+            selected: None,
+            last_name: String::new(),
+            file_path: "data.gestures".to_owned(),
+            dirty: true,
+        }
+    }
+}
+
+enum PromptKind {
+    TemplateName(Path2D),
+    SavePath,
+    LoadPath,
+}
+
+/// A file operation requested from a path prompt.
+#[derive(Event)]
+enum FileRequest {
+    Save(String),
+    Load(String),
+}
+
+/// The path and outcome of a finished load.
+type LoadResult = (String, Result<Vec<Template>, String>);
+
+/// The result of the most recent load, waiting to replace the current templates.
+#[derive(Default, Resource)]
+struct PendingLoad(Arc<Mutex<Option<LoadResult>>>);
+
+/// A single-line text prompt that captures keyboard input until confirmed or cancelled.
+#[derive(Default, Resource)]
+struct Prompt {
+    active: Option<(PromptKind, String)>,
+}
+
 fn main() {
+    // Templates are saved and loaded through a `user://` asset source rooted in the working directory.
+    // A shipped app would root it in the platform's data directory instead.
+    let user_dir = env::current_dir().unwrap().to_string_lossy().into_owned();
+
     App::new()
         .insert_resource(Msaa::Off)
         .insert_resource(ClearColor(Color::rgb(0.0, 0.0, 0.0)))
         .init_resource::<RecordState>()
+        .init_resource::<Library>()
+        .init_resource::<Prompt>()
+        .init_resource::<PendingLoad>()
         .add_event::<VisiblePathEvent>()
         .add_event::<TextEvent>()
+        .add_event::<FileRequest>()
         .add_systems(Update, (
+            (prompt_input, keyboard_input).chain(),
+            (file_requests, loaded_templates, saved_templates),
+            show_progress.before(recorded_path),
             recorded_path,
-            keyboard_input,
             create_visible_path,
             fade_visible_path,
             update_text,
+            update_prompt_text,
+            update_template_list,
         ))
         .add_systems(Startup, setup)
+        .register_asset_source("user", AssetSource::build()
+            .with_reader(AssetSource::get_default_reader(user_dir.clone()))
+            .with_writer(AssetSource::get_default_writer(user_dir)))
         .add_plugins(DefaultPlugins.set(WindowPlugin {
             primary_window: Some(Window {
                 title: "Gesture trainer".to_string(),
@@ -52,7 +127,13 @@ fn setup(
 
     commands.spawn((
         TextBundle::from_section(
-            "Space: record a template\nShift: attempt a gesture\nEnter: save all templates\nO: load templates",
+            "Space: record a template\n\
+             Shift: attempt a gesture\n\
+             Up/Down: select a template\n\
+             R: re-record the selected template\n\
+             Delete: delete the selected template\n\
+             S: save all templates\n\
+             O: load templates",
             TextStyle {
                 font_size: 20.0,
                 color: Color::WHITE,
@@ -133,9 +214,13 @@ fn recorded_path(
     mut state: ResMut<GestureState>,
     mut path_events: EventWriter<VisiblePathEvent>,
     mut record_state: ResMut<RecordState>,
+    mut library: ResMut<Library>,
+    mut prompt: ResMut<Prompt>,
+    mut ui_events: EventWriter<TextEvent>,
 ) {
     for event in events.read() {
-        match record_state.state.as_ref().unwrap() {
+        let Some(record_type) = record_state.state.take() else { continue };
+        match record_type {
             RecordType::Attempt => {
                 let matched_template = find_matching_template_with_defaults(
                     &state.templates,
@@ -144,6 +229,7 @@ fn recorded_path(
                 match matched_template {
                     Ok((template, score)) if score >= 0.8 => {
                         println!("matched {} with score {}", template.name, score);
+                        ui_events.send(TextEvent::Show(format!("Matched {}", template.name)));
                         path_events.send(VisiblePathEvent {
                             color: Color::GREEN,
                             path: event.path.points(),
@@ -151,46 +237,172 @@ fn recorded_path(
                     }
                     Ok((template, score)) => {
                         println!("matched {} but with score {}", template.name, score);
+                        ui_events.send(TextEvent::Hide);
+                    }
+                    Err(err) => {
+                        println!("failed to match: {:?}", err);
+                        ui_events.send(TextEvent::Hide);
                     }
-                    Err(err) => println!("failed to match: {:?}", err),
                 }
             }
 
             RecordType::Template => {
-                let Ok(template) = Template::new(
-                    state.templates.len().to_string(),
-                    &event.path,
-                ) else {
+                if event.path.points().is_empty() {
                     continue;
+                }
+                path_events.send(VisiblePathEvent {
+                    color: Color::BLUE,
+                    path: event.path.points(),
+                });
+                prompt.active = Some((
+                    PromptKind::TemplateName(event.path.clone()),
+                    library.last_name.clone(),
+                ));
+            }
+
+            RecordType::Rerecord(index) => {
+                let Some(existing) = state.templates.get(index) else { continue };
+                let template = match Template::new(existing.name.clone(), &event.path) {
+                    Ok(template) => template,
+                    Err(err) => {
+                        println!("failed to re-record template: {:?}", err);
+                        ui_events.send(TextEvent::Show("Error re-recording template".to_owned()));
+                        continue;
+                    }
                 };
-                println!("done recording template {}", template.name);
-                state.templates.push(template);
+                println!("re-recorded template {}", template.name);
+                ui_events.send(TextEvent::Show(format!("Re-recorded {}", template.name)));
+                state.templates[index] = template;
+                library.dirty = true;
                 path_events.send(VisiblePathEvent {
                     color: Color::BLUE,
                     path: event.path.points(),
                 });
             }
         }
+    }
+}
+
+fn show_progress(
+    mut events: EventReader<GestureProgress>,
+    record_state: Res<RecordState>,
+    mut ui_events: EventWriter<TextEvent>,
+) {
+    let Some(event) = events.read().last() else { return };
+    if !matches!(record_state.state, Some(RecordType::Attempt)) {
+        return;
+    }
+    let text = match event.candidates.first() {
+        Some(best) => format!("Recording: {} ({:.0}%)", best.name, best.progress * 100.0),
+        None => "Recording".to_owned(),
+    };
+    ui_events.send(TextEvent::Show(text));
+}
+
+/// Files are read and written through the `user` asset source.
+fn user_path(path: &str) -> AssetPath<'static> {
+    AssetPath::from(path.to_owned()).with_source("user")
+}
+
+async fn read_templates(
+    server: &AssetServer,
+    path: &str,
+) -> Result<Vec<Template>, Box<dyn Error + Send + Sync>> {
+    let path = user_path(path);
+    let source = server.get_source(path.source().clone())?;
+    let mut reader = source.reader().read(path.path()).await?;
+    let mut serialized = String::new();
+    reader.read_to_string(&mut serialized).await?;
+    let mut loaded = GestureState::default();
+    loaded.deserialize_templates(&serialized)?;
+    Ok(loaded.templates)
+}
+
+fn file_requests(
+    mut requests: EventReader<FileRequest>,
+    mut save_events: EventWriter<SaveGestureTemplates>,
+    server: Res<AssetServer>,
+    pending_load: Res<PendingLoad>,
+) {
+    for request in requests.read() {
+        match request {
+            FileRequest::Save(path) => {
+                save_events.send(SaveGestureTemplates::new(user_path(path)));
+            }
+            FileRequest::Load(path) => {
+                let server = server.clone();
+                let path = path.clone();
+                let pending = pending_load.0.clone();
+                IoTaskPool::get()
+                    .spawn(async move {
+                        let result = read_templates(&server, &path).await;
+                        let result = result.map_err(|err| err.to_string());
+                        *pending.lock().unwrap() = Some((path, result));
+                    })
+                    .detach();
+            }
+        }
+    }
+}
+
+/// Stops a re-recording in progress, whose template index may no longer be valid.
+fn cancel_rerecord(record_state: &mut RecordState, record_events: &mut EventWriter<GestureRecord>) {
+    if let Some(RecordType::Rerecord(_)) = record_state.state {
         record_state.state = None;
+        record_events.send(GestureRecord::Stop);
+    }
+}
+
+fn loaded_templates(
+    pending_load: Res<PendingLoad>,
+    mut state: ResMut<GestureState>,
+    mut library: ResMut<Library>,
+    mut record_state: ResMut<RecordState>,
+    mut record_events: EventWriter<GestureRecord>,
+    mut ui_events: EventWriter<TextEvent>,
+) {
+    let Some((path, result)) = pending_load.0.lock().unwrap().take() else { return };
+    match result {
+        Ok(templates) => {
+            cancel_rerecord(&mut record_state, &mut record_events);
+            state.templates = templates;
+            ui_events.send(TextEvent::Show(format!("Loaded templates from {}", path)));
+            library.selected = None;
+            library.dirty = true;
+        }
+        Err(err) => {
+            ui_events.send(TextEvent::Show(format!("Error loading templates: {}", err)));
+        }
     }
 }
 
-fn save_templates(state: &GestureState) -> Result<(), ()> {
-    let serialized = state.serialize_templates()?;
-    let path = Path::new("data.gestures");
-    let mut f = File::create(path).map_err(|_| ())?;
-    f.write(serialized.as_bytes()).map_err(|_| ())?;
-    Ok(())
+fn saved_templates(
+    mut events: EventReader<GestureTemplatesSaved>,
+    mut ui_events: EventWriter<TextEvent>,
+) {
+    for event in events.read() {
+        let path = event.path.path().display();
+        let text = match event.result {
+            Ok(()) => format!("Saved templates to {}", path),
+            Err(ref err) => format!("Error saving templates: {}", err),
+        };
+        ui_events.send(TextEvent::Show(text));
+    }
 }
 
 fn keyboard_input(
     keys: Res<ButtonInput<KeyCode>>,
     mut record_events: EventWriter<GestureRecord>,
     mut ui_events: EventWriter<TextEvent>,
-    state: Res<GestureState>,
-    server: Res<AssetServer>,
+    mut state: ResMut<GestureState>,
     mut record_state: ResMut<RecordState>,
+    mut library: ResMut<Library>,
+    mut prompt: ResMut<Prompt>,
 ) {
+    if prompt.active.is_some() {
+        return;
+    }
+
     if keys.just_pressed(KeyCode::ShiftLeft) {
         record_state.state = Some(RecordType::Attempt);
         record_events.send(GestureRecord::Start);
@@ -211,21 +423,291 @@ fn keyboard_input(
         ui_events.send(TextEvent::Hide);
     }
 
-    if keys.just_released(KeyCode::Enter) {
-         match save_templates(&state) {
-            Ok(()) => {
-                ui_events.send(TextEvent::Show("Saved templates".to_owned()));
+    if keys.just_pressed(KeyCode::KeyR) {
+        if let Some(selected) = library.selected {
+            record_state.state = Some(RecordType::Rerecord(selected));
+            record_events.send(GestureRecord::Start);
+            ui_events.send(TextEvent::Show(
+                format!("Re-recording {}", state.templates[selected].name)
+            ));
+        }
+    }
+    if keys.just_released(KeyCode::KeyR) && library.selected.is_some() {
+        record_events.send(GestureRecord::Stop);
+        ui_events.send(TextEvent::Hide);
+    }
+
+    let num_templates = state.templates.len();
+    if keys.just_pressed(KeyCode::ArrowUp) && num_templates > 0 {
+        library.selected = Some(match library.selected {
+            Some(0) | None => num_templates - 1,
+            Some(selected) => selected - 1,
+        });
+        library.dirty = true;
+    }
+    if keys.just_pressed(KeyCode::ArrowDown) && num_templates > 0 {
+        library.selected = Some(match library.selected {
+            Some(selected) if selected + 1 < num_templates => selected + 1,
+            _ => 0,
+        });
+        library.dirty = true;
+    }
+
+    if keys.any_just_pressed([KeyCode::Delete, KeyCode::Backspace]) {
+        if let Some(selected) = library.selected {
+            // Deleting shifts the indices of later templates, so a re-recording in progress
+            // could replace the wrong one.
+            cancel_rerecord(&mut record_state, &mut record_events);
+            let removed = state.templates.remove(selected);
+            ui_events.send(TextEvent::Show(format!("Deleted {}", removed.name)));
+            library.selected = match state.templates.len() {
+                0 => None,
+                len => Some(selected.min(len - 1)),
+            };
+            library.dirty = true;
+        }
+    }
+
+    if keys.just_released(KeyCode::KeyS) {
+        prompt.active = Some((PromptKind::SavePath, library.file_path.clone()));
+    }
+
+    if keys.just_released(KeyCode::KeyO) {
+        prompt.active = Some((PromptKind::LoadPath, library.file_path.clone()));
+    }
+}
+
+fn prompt_input(
+    mut characters: EventReader<ReceivedCharacter>,
+    mut key_events: EventReader<KeyboardInput>,
+    mut prompt: ResMut<Prompt>,
+    mut state: ResMut<GestureState>,
+    mut library: ResMut<Library>,
+    mut ui_events: EventWriter<TextEvent>,
+    mut file_events: EventWriter<FileRequest>,
+) {
+    // Always drain pending input so that keys pressed before a prompt opens
+    // are not replayed into it.
+    let characters: Vec<_> = characters.read().collect();
+    let key_events: Vec<_> = key_events.read().collect();
+    let Some((_, ref mut text)) = prompt.active else { return };
+
+    for ev in characters {
+        text.extend(ev.char.chars().filter(|c| !c.is_control()));
+    }
+
+    let mut confirmed = false;
+    for ev in key_events {
+        if ev.state != ButtonState::Pressed {
+            continue;
+        }
+        match ev.key_code {
+            KeyCode::Backspace => {
+                text.pop();
             }
-            Err(()) => {
-                ui_events.send(TextEvent::Show("Error saving templates".to_owned()));
+            KeyCode::Enter => confirmed = true,
+            KeyCode::Escape => {
+                prompt.active = None;
+                ui_events.send(TextEvent::Show("Cancelled".to_owned()));
+                return;
             }
+            _ => {}
         }
     }
+    if !confirmed {
+        return;
+    }
 
-    if keys.just_released(KeyCode::KeyO) {
-        let _handle: Handle<GestureTemplates> = server.load("data.gestures");
-        ui_events.send(TextEvent::Show("Loading templates".to_owned()));
+    let Some((kind, text)) = prompt.active.take() else { return };
+    let text = text.trim().to_owned();
+    if text.is_empty() {
+        ui_events.send(TextEvent::Show("Cancelled".to_owned()));
+        return;
     }
+
+    match kind {
+        PromptKind::TemplateName(path) => {
+            let template = match Template::new(text.clone(), &path) {
+                Ok(template) => template,
+                Err(err) => {
+                    println!("failed to record template: {:?}", err);
+                    ui_events.send(TextEvent::Show("Error recording template".to_owned()));
+                    return;
+                }
+            };
+            println!("done recording template {}", template.name);
+            ui_events.send(TextEvent::Show(format!("Recorded {}", template.name)));
+            state.templates.push(template);
+            library.selected = Some(state.templates.len() - 1);
+            library.last_name = text;
+            library.dirty = true;
+        }
+
+        PromptKind::SavePath => {
+            file_events.send(FileRequest::Save(text.clone()));
+            ui_events.send(TextEvent::Show(format!("Saving templates to {}", text)));
+            library.file_path = text;
+        }
+
+        PromptKind::LoadPath => {
+            file_events.send(FileRequest::Load(text.clone()));
+            ui_events.send(TextEvent::Show(format!("Loading templates from {}", text)));
+            library.file_path = text;
+        }
+    }
+}
+
+#[derive(Component)]
+struct PromptText;
+
+fn update_prompt_text(
+    prompt: Res<Prompt>,
+    query: Query<Entity, With<PromptText>>,
+    mut commands: Commands,
+) {
+    if !prompt.is_changed() {
+        return;
+    }
+    for entity in &query {
+        commands.entity(entity).despawn();
+    }
+
+    let Some((ref kind, ref text)) = prompt.active else { return };
+    let label = match kind {
+        PromptKind::TemplateName(_) => "Template name",
+        PromptKind::SavePath => "Save to",
+        PromptKind::LoadPath => "Load from",
+    };
+    commands.spawn((
+        PromptText,
+        TextBundle::from_section(
+            format!("{}: {}_\n(Enter to confirm, Escape to cancel)", label, text),
+            TextStyle {
+                font_size: 30.0,
+                color: Color::WHITE,
+                ..default()
+            }
+        ).with_style(
+            Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(70.0),
+                left: Val::Px(15.0),
+                ..default()
+            },
+        ),
+    ));
+}
+
+#[derive(Component)]
+struct TemplateList;
+
+const THUMBNAIL_SIZE: f32 = 48.;
+const THUMBNAIL_DOT: f32 = 2.;
+
+fn update_template_list(
+    state: Res<GestureState>,
+    mut library: ResMut<Library>,
+    query: Query<Entity, With<TemplateList>>,
+    mut commands: Commands,
+) {
+    if !library.dirty {
+        return;
+    }
+    library.dirty = false;
+
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    commands
+        .spawn((
+            TemplateList,
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(5.0),
+                    right: Val::Px(15.0),
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(4.0),
+                    ..default()
+                },
+                ..default()
+            },
+        ))
+        .with_children(|list| {
+            for (index, template) in state.templates.iter().enumerate() {
+                let background = if library.selected == Some(index) {
+                    Color::rgb(0.3, 0.3, 0.6)
+                } else {
+                    Color::NONE
+                };
+                list
+                    .spawn(NodeBundle {
+                        style: Style {
+                            align_items: AlignItems::Center,
+                            column_gap: Val::Px(8.0),
+                            padding: UiRect::all(Val::Px(2.0)),
+                            ..default()
+                        },
+                        background_color: background.into(),
+                        ..default()
+                    })
+                    .with_children(|row| {
+                        spawn_thumbnail(row, &template.path.points());
+                        row.spawn(TextBundle::from_section(
+                            format!("{}: {}", index, template.name),
+                            TextStyle {
+                                font_size: 20.0,
+                                color: Color::WHITE,
+                                ..default()
+                            },
+                        ));
+                    });
+            }
+        });
+}
+
+/// Draw the normalized points of a template, scaled to fit a small square.
+fn spawn_thumbnail(parent: &mut ChildBuilder, points: &[(f32, f32)]) {
+    let (mut min_x, mut min_y) = (f32::MAX, f32::MAX);
+    let (mut max_x, mut max_y) = (f32::MIN, f32::MIN);
+    for &(x, y) in points {
+        min_x = min_x.min(x);
+        max_x = max_x.max(x);
+        min_y = min_y.min(y);
+        max_y = max_y.max(y);
+    }
+    let extent = (max_x - min_x).max(max_y - min_y).max(f32::EPSILON);
+    let scale = (THUMBNAIL_SIZE - THUMBNAIL_DOT) / extent;
+
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Px(THUMBNAIL_SIZE),
+                height: Val::Px(THUMBNAIL_SIZE),
+                ..default()
+            },
+            background_color: Color::rgb(0.15, 0.15, 0.15).into(),
+            ..default()
+        })
+        .with_children(|thumbnail| {
+            for (i, &(x, y)) in points.iter().enumerate() {
+                // Highlight the start point so the stroke direction is visible.
+                let color = if i == 0 { Color::GREEN } else { Color::WHITE };
+                thumbnail.spawn(NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        left: Val::Px((x - min_x) * scale),
+                        top: Val::Px((y - min_y) * scale),
+                        width: Val::Px(THUMBNAIL_DOT),
+                        height: Val::Px(THUMBNAIL_DOT),
+                        ..default()
+                    },
+                    background_color: color.into(),
+                    ..default()
+                });
+            }
+        });
 }
 
 #[derive(Event)]
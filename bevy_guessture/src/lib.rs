@@ -1,8 +1,19 @@
+use bevy::asset::io::{
+    AssetWriterError, MissingAssetSourceError, MissingAssetWriterError, Reader,
+};
+use bevy::asset::{AssetLoader, AssetPath, AsyncReadExt, LoadContext};
 use bevy::prelude::*;
 use bevy::reflect::TypePath;
-use bevy_common_assets::json::JsonAssetPlugin;
+use bevy::tasks::IoTaskPool;
+use bevy::utils::BoxedFuture;
+use guessture::binary::Encoding;
+use guessture::filter::{FilterChain, PointFilter};
+use guessture::format::GestureFile;
+use guessture::incremental::{Candidate, IncrementalRecognizer};
+use guessture::segment::{Segmenter, SegmenterConfig};
 pub use guessture::*;
-use std::mem;
+use std::sync::{Arc, Mutex};
+use std::{fmt, io, mem};
 
 /// Plugin object to automatically integrate gesture recognition into your Bevy app.
 #[derive(Default)]
@@ -11,18 +22,26 @@ pub struct GuessturePlugin;
 impl Plugin for GuessturePlugin {
     fn build(&self, app: &mut App) {
         app
-            .add_plugins(
-                JsonAssetPlugin::<GestureTemplates>::new(&["gestures"])
-            )
+            .init_asset::<GestureTemplates>()
+            .init_asset_loader::<GestureTemplatesLoader>()
+            .init_asset_loader::<BinaryGestureTemplatesLoader>()
             .add_systems(Update, (
                 change_recording_state,
-                update_templates,
-                record_mouse
-                    .run_if(|state: Res<GestureState>| state.current_recording.is_some())
+                (update_templates, update_recognizer).chain(),
+                (record_mouse, report_progress)
+                    .chain()
+                    .run_if(|state: Res<GestureState>| state.current_recording.is_some()),
+                segment_mouse.run_if(resource_exists::<GestureSegmenter>),
+                (start_saves, finish_saves).chain(),
             ))
             .add_event::<GestureRecord>()
             .add_event::<RecordedPath>()
-            .init_resource::<GestureState>();
+            .add_event::<GestureProgress>()
+            .add_event::<RecognizedGesture>()
+            .add_event::<SaveGestureTemplates>()
+            .add_event::<GestureTemplatesSaved>()
+            .init_resource::<GestureState>()
+            .init_resource::<FinishedSaves>();
     }
 }
 
@@ -32,22 +51,33 @@ impl Plugin for GuessturePlugin {
 pub struct GestureState {
     pub templates: Vec<Template>,
     current_recording: Option<Path2D>,
+    /// Matches partial recordings against `templates`. Rebuilt whenever this resource is
+    /// changed, so the recording systems leave the change flag alone.
+    recognizer: Option<IncrementalRecognizer>,
 }
 
 impl GestureState {
     /// Serialize all gesture templates as JSON. The result can be writtent
     /// to a `.gestures` file and subsequently loaded by Bevy as an asset.
-    pub fn serialize_templates(&self) -> Result<String, ()> {
-        let templates = GestureTemplates {
-            templates: self.templates
-                .iter()
-                .map(|template| TemplateData {
-                    name: template.name.clone(),
-                    path: template.path.points(),
-                })
-                .collect(),
-        };
-        serde_json::to_string(&templates).map_err(|_| ())
+    /// To write the file through Bevy's asset sources, send a
+    /// [SaveGestureTemplates] event instead.
+    pub fn serialize_templates(&self) -> Result<String, format::Error> {
+        Ok(GestureFile::from_templates(&self.templates).to_json()?)
+    }
+
+    /// Serialize all gesture templates in the compact binary format. The result can be
+    /// written to a `.gestures.bin` file and subsequently loaded by Bevy as an asset.
+    pub fn serialize_templates_binary(&self, encoding: Encoding) -> Vec<u8> {
+        binary::encode(&self.templates, encoding)
+    }
+
+    /// Deserialize gesture templates from JSON previously produced by
+    /// [GestureState::serialize_templates], appending them to the current
+    /// set of templates.
+    pub fn deserialize_templates(&mut self, serialized: &str) -> Result<(), format::Error> {
+        let file = GestureFile::from_json(serialized)?;
+        self.templates.extend(file.to_templates()?);
+        Ok(())
     }
 }
 
@@ -71,14 +101,30 @@ pub struct RecordedPath {
     pub path: Path2D,
 }
 
+/// An event sent every frame while recording, describing which gestures the
+/// path recorded so far may turn out to be.
+#[derive(Event)]
+pub struct GestureProgress {
+    /// One candidate per template, ordered from most to least likely. Empty if the
+    /// path recorded so far is too short to compare.
+    pub candidates: Vec<Candidate>,
+}
+
 fn change_recording_state(
     mut events: EventReader<GestureRecord>,
     mut state: ResMut<GestureState>,
     mut path_event: EventWriter<RecordedPath>,
+    mut filter: Option<ResMut<GestureFilter>>,
 ) {
+    let state = state.bypass_change_detection();
     for event in events.read() {
         match event {
-            GestureRecord::Start => state.current_recording = Some(Path2D::default()),
+            GestureRecord::Start => {
+                if let Some(ref mut filter) = filter {
+                    filter.0.reset();
+                }
+                state.current_recording = Some(Path2D::default());
+            }
             GestureRecord::Stop => {
                 let Some(path) = mem::take(&mut state.current_recording) else { continue };
                 path_event.send(RecordedPath {
@@ -89,13 +135,26 @@ fn change_recording_state(
     }
 }
 
+/// Insert this resource to filter mouse positions while a gesture is being recorded,
+/// before they are added to the recorded path. The filters are reset whenever a new
+/// recording starts.
+#[derive(Resource, Default)]
+pub struct GestureFilter(pub FilterChain);
+
 fn record_mouse(
     mut cursor_evr: EventReader<CursorMoved>,
     mut state: ResMut<GestureState>,
+    time: Res<Time>,
+    mut filter: Option<ResMut<GestureFilter>>,
 ) {
-    if let Some(ref mut path) = state.current_recording {
-        for ev in cursor_evr.read() {
-            let (x, y) = (ev.position.x, ev.position.y);
+    if let Some(ref mut path) = state.bypass_change_detection().current_recording {
+        let events: Vec<_> = cursor_evr.read().collect();
+        for (ev, time) in events.iter().zip(event_times(&time, events.len())) {
+            let (mut x, mut y) = (ev.position.x, ev.position.y);
+            if let Some(ref mut filter) = filter {
+                let Some(filtered) = filter.0.filter(x, y, time) else { continue };
+                (x, y) = filtered;
+            }
             if path.is_new_point(x, y) {
                 path.push(x, y);
             }
@@ -103,17 +162,323 @@ fn record_mouse(
     }
 }
 
-/// An asset format for serialized guesture templat data. Load a `.guessture` file to
-/// automatically update [GestureState::templates] when the asset is completely loaded.
-#[derive(serde::Deserialize, serde::Serialize, Asset, TypePath)]
+/// Split the templates into prefixes again after they change, rather than every time a
+/// recording starts.
+fn update_recognizer(mut state: ResMut<GestureState>) {
+    if !state.is_changed() {
+        return;
+    }
+    let state = state.bypass_change_detection();
+    state.recognizer = Some(IncrementalRecognizer::new(&state.templates));
+}
+
+fn report_progress(
+    state: Res<GestureState>,
+    mut progress_event: EventWriter<GestureProgress>,
+) {
+    let (Some(path), Some(recognizer)) = (&state.current_recording, &state.recognizer) else {
+        return
+    };
+    progress_event.send(GestureProgress {
+        candidates: recognizer.recognize(path).unwrap_or_default(),
+    });
+}
+
+/// Insert this resource to recognize gestures continuously from mouse movement,
+/// without sending [GestureRecord] events. Gestures are detected when the mouse
+/// pauses or slows down, or when the match score of the movement so far peaks, and are
+/// reported through [RecognizedGesture] events.
+#[derive(Resource)]
+pub struct GestureSegmenter(pub Segmenter);
+
+impl Default for GestureSegmenter {
+    fn default() -> Self {
+        GestureSegmenter(Segmenter::new(SegmenterConfig::default()))
+    }
+}
+
+/// An event sent when a [GestureSegmenter] finds a gesture in the mouse movement.
+#[derive(Event)]
+pub struct RecognizedGesture {
+    /// The name of the matching template.
+    pub name: String,
+    /// The score of the match, between 0.0 and 1.0.
+    pub score: f32,
+    /// The mouse positions that were recognized as the gesture.
+    pub path: Path2D,
+}
+
+/// Returns timestamps for `count` cursor events received during the last frame. Bevy only
+/// reports when the frame happened, so the events are spread evenly across the frame
+/// instead of all arriving at once, which would look like a burst of infinite speed.
+fn event_times(time: &Time, count: usize) -> impl Iterator<Item = f32> {
+    let (now, delta) = (time.elapsed_seconds(), time.delta_seconds());
+    (1..=count).map(move |i| now - delta + delta * i as f32 / count as f32)
+}
+
+fn segment_mouse(
+    mut cursor_evr: EventReader<CursorMoved>,
+    time: Res<Time>,
+    state: Res<GestureState>,
+    mut segmenter: ResMut<GestureSegmenter>,
+    mut gesture_event: EventWriter<RecognizedGesture>,
+) {
+    let events: Vec<_> = cursor_evr.read().collect();
+    let mut segments = vec![];
+    for (ev, time) in events.iter().zip(event_times(&time, events.len())) {
+        segments.extend(segmenter.0.push(&state.templates, ev.position.x, ev.position.y, time));
+    }
+    segments.extend(segmenter.0.update(&state.templates, time.elapsed_seconds()));
+
+    for segment in segments {
+        gesture_event.send(RecognizedGesture {
+            name: state.templates[segment.template].name.clone(),
+            score: segment.score,
+            path: segment.path,
+        });
+    }
+}
+
+/// An asset format for serialized guesture templat data. Load a `.gestures` file, or a
+/// `.gestures.bin` file in the [binary] format, to automatically update
+/// [GestureState::templates] when the asset is completely loaded. Files are validated and
+/// migrated to the current schema when they are loaded; files that fail validation are
+/// reported through [AssetLoadFailedEvent](bevy::asset::AssetLoadFailedEvent) instead.
+#[derive(Asset, TypePath)]
 pub struct GestureTemplates {
-    templates: Vec<TemplateData>,
+    file: GestureFile,
 }
 
-#[derive(serde::Deserialize, serde::Serialize)]
-struct TemplateData {
-    name: String,
-    path: Vec<(f32, f32)>,
+/// An error encountered while loading [GestureTemplates].
+#[derive(Debug)]
+pub enum GestureTemplatesError {
+    /// The file could not be read.
+    Io(io::Error),
+    /// The contents of the file were not valid.
+    Format(format::Error),
+}
+
+impl fmt::Display for GestureTemplatesError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GestureTemplatesError::Io(err) => write!(f, "{}", err),
+            GestureTemplatesError::Format(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for GestureTemplatesError {}
+
+impl From<io::Error> for GestureTemplatesError {
+    fn from(err: io::Error) -> GestureTemplatesError {
+        GestureTemplatesError::Io(err)
+    }
+}
+
+impl From<format::Error> for GestureTemplatesError {
+    fn from(err: format::Error) -> GestureTemplatesError {
+        GestureTemplatesError::Format(err)
+    }
+}
+
+/// Loads [GestureTemplates] from `.gestures` files.
+#[derive(Default)]
+pub struct GestureTemplatesLoader;
+
+impl AssetLoader for GestureTemplatesLoader {
+    type Asset = GestureTemplates;
+    type Settings = ();
+    type Error = GestureTemplatesError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<GestureTemplates, GestureTemplatesError>> {
+        Box::pin(async move {
+            let mut json = String::new();
+            reader.read_to_string(&mut json).await?;
+            let file = GestureFile::from_json(&json)?;
+            Ok(GestureTemplates {
+                file,
+            })
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["gestures"]
+    }
+}
+
+/// Loads [GestureTemplates] from `.gestures.bin` files in the [binary] format.
+#[derive(Default)]
+pub struct BinaryGestureTemplatesLoader;
+
+impl AssetLoader for BinaryGestureTemplatesLoader {
+    type Asset = GestureTemplates;
+    type Settings = ();
+    type Error = GestureTemplatesError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<GestureTemplates, GestureTemplatesError>> {
+        Box::pin(async move {
+            let mut bytes = vec![];
+            reader.read_to_end(&mut bytes).await?;
+            let file = GestureFile::from_binary(&bytes)?;
+            Ok(GestureTemplates {
+                file,
+            })
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["gestures.bin"]
+    }
+}
+
+/// An error encountered while saving gesture templates.
+#[derive(Debug)]
+pub enum SaveTemplatesError {
+    /// The path names an asset source that is not registered.
+    MissingSource(MissingAssetSourceError),
+    /// The asset source cannot be written to, as is the default source on the web.
+    MissingWriter(MissingAssetWriterError),
+    /// The asset source could not write the file.
+    Writer(AssetWriterError),
+    /// The templates could not be serialized.
+    Format(format::Error),
+}
+
+impl fmt::Display for SaveTemplatesError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SaveTemplatesError::MissingSource(err) => write!(f, "{}", err),
+            SaveTemplatesError::MissingWriter(err) => write!(f, "{}", err),
+            SaveTemplatesError::Writer(err) => write!(f, "{}", err),
+            SaveTemplatesError::Format(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for SaveTemplatesError {}
+
+impl From<MissingAssetSourceError> for SaveTemplatesError {
+    fn from(err: MissingAssetSourceError) -> SaveTemplatesError {
+        SaveTemplatesError::MissingSource(err)
+    }
+}
+
+impl From<MissingAssetWriterError> for SaveTemplatesError {
+    fn from(err: MissingAssetWriterError) -> SaveTemplatesError {
+        SaveTemplatesError::MissingWriter(err)
+    }
+}
+
+impl From<AssetWriterError> for SaveTemplatesError {
+    fn from(err: AssetWriterError) -> SaveTemplatesError {
+        SaveTemplatesError::Writer(err)
+    }
+}
+
+impl From<format::Error> for SaveTemplatesError {
+    fn from(err: format::Error) -> SaveTemplatesError {
+        SaveTemplatesError::Format(err)
+    }
+}
+
+/// An event to save every template in [GestureState::templates] to a `.gestures`
+/// file. The file is written in the background by the asset source named by `path`,
+/// so templates can be stored anywhere an asset source can write to, e.g. a `user://`
+/// source registered for the platform's data directory. A [GestureTemplatesSaved]
+/// event is sent once the file has been written.
+///
+/// Saving does not go through an `AssetSaver`: the templates are serialized directly and
+/// the bytes are handed to the asset source's `AssetWriter`, which is the part to replace
+/// to store templates elsewhere. Files are always written as JSON, whatever the path's
+/// extension; when loading, the extension picks the format, so save to a path ending in
+/// `.gestures` to load the file again.
+#[derive(Event)]
+pub struct SaveGestureTemplates {
+    /// The asset path to write the templates to, including its asset source.
+    pub path: AssetPath<'static>,
+}
+
+impl SaveGestureTemplates {
+    /// Create an event that saves the templates to `path`, e.g. `"user://custom.gestures"`.
+    pub fn new(path: impl Into<AssetPath<'static>>) -> SaveGestureTemplates {
+        SaveGestureTemplates {
+            path: path.into(),
+        }
+    }
+}
+
+/// An event following a [SaveGestureTemplates] event, once the templates have been saved
+/// or saving them has failed.
+#[derive(Event)]
+pub struct GestureTemplatesSaved {
+    /// The path that the templates were saved to.
+    pub path: AssetPath<'static>,
+    /// Whether the templates were written, or why they could not be.
+    pub result: Result<(), SaveTemplatesError>,
+}
+
+/// The results of saves that have finished in the background, waiting to be sent as
+/// [GestureTemplatesSaved] events.
+#[derive(Default, Resource)]
+struct FinishedSaves(Arc<Mutex<Vec<GestureTemplatesSaved>>>);
+
+async fn write_templates(
+    server: &AssetServer,
+    path: &AssetPath<'static>,
+    json: &str,
+) -> Result<(), SaveTemplatesError> {
+    let writer = server.get_source(path.source().clone())?.writer()?;
+    writer.write_bytes(path.path(), json.as_bytes()).await?;
+    Ok(())
+}
+
+fn start_saves(
+    mut events: EventReader<SaveGestureTemplates>,
+    state: Res<GestureState>,
+    server: Res<AssetServer>,
+    finished: Res<FinishedSaves>,
+) {
+    for event in events.read() {
+        let path = event.path.clone();
+        let json = match state.serialize_templates() {
+            Ok(json) => json,
+            Err(err) => {
+                finished.0.lock().unwrap().push(GestureTemplatesSaved {
+                    path,
+                    result: Err(err.into()),
+                });
+                continue;
+            }
+        };
+        let (server, finished) = (server.clone(), finished.0.clone());
+        IoTaskPool::get()
+            .spawn(async move {
+                let result = write_templates(&server, &path, &json).await;
+                finished.lock().unwrap().push(GestureTemplatesSaved {
+                    path,
+                    result,
+                });
+            })
+            .detach();
+    }
+}
+
+fn finish_saves(
+    finished: Res<FinishedSaves>,
+    mut saved_event: EventWriter<GestureTemplatesSaved>,
+) {
+    let saved = mem::take(&mut *finished.0.lock().unwrap());
+    saved_event.send_batch(saved);
 }
 
 fn update_templates(
@@ -125,17 +490,9 @@ fn update_templates(
         match ev {
             AssetEvent::LoadedWithDependencies { id } => {
                 let gestures = assets.get(*id).unwrap();
-                for template_data in &gestures.templates {
-                    let mut path = Path2D::default();
-                    for &(x, y) in &template_data.path {
-                        path.push(x, y);
-                    }
-                    let Ok(template) = Template::new_from_template(
-                        template_data.name.clone(), path
-                    ) else {
-                        continue
-                    };
-                    state.templates.push(template);
+                match gestures.file.to_templates() {
+                    Ok(templates) => state.templates.extend(templates),
+                    Err(err) => error!("invalid gesture templates: {}", err),
                 }
             }
 
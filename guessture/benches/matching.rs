@@ -0,0 +1,89 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use guessture::augment::{Augmentation, Augmenter};
+use guessture::{
+    find_matching_template,
+    find_matching_template_with_scratch,
+    MatchScratch,
+    Path2D,
+    Template,
+};
+
+/// A path through the provided corners with evenly spaced points along each edge.
+fn polyline(points: &[(f32, f32)]) -> Path2D {
+    let mut path = Path2D::default();
+    for pair in points.windows(2) {
+        let [(x0, y0), (x1, y1)] = pair else { unreachable!() };
+        for i in 0..20 {
+            let t = i as f32 / 20.0;
+            path.push(x0 + (x1 - x0) * t, y0 + (y1 - y0) * t);
+        }
+    }
+    let &(x, y) = points.last().unwrap();
+    path.push(x, y);
+    path
+}
+
+fn templates() -> Vec<Template> {
+    let shapes: [&[(f32, f32)]; 8] = [
+        &[(0., 0.), (200., 0.), (200., 200.), (0., 200.), (0., 0.)],
+        &[(0., 0.), (200., 0.), (100., 170.), (0., 0.)],
+        &[(0., 0.), (200., 0.), (0., 200.), (200., 200.)],
+        &[(0., 0.), (100., 200.), (200., 0.)],
+        &[(0., 200.), (100., 0.), (200., 200.)],
+        &[(0., 0.), (0., 200.), (200., 200.)],
+        &[(0., 0.), (200., 200.), (200., 0.), (0., 200.)],
+        &[(0., 100.), (100., 0.), (200., 100.), (100., 200.), (0., 100.)],
+    ];
+    shapes
+        .iter()
+        .enumerate()
+        .map(|(i, shape)| Template::new(format!("shape{}", i), &polyline(shape)).unwrap())
+        .collect()
+}
+
+fn matching(c: &mut Criterion) {
+    let templates = templates();
+    let path = polyline(&[(10., 5.), (190., 20.), (0., 210.), (205., 190.)]);
+
+    c.bench_function("find_matching_template", |b| {
+        b.iter(|| find_matching_template(black_box(&templates), black_box(&path), 45.0, 2.0))
+    });
+
+    let mut scratch = MatchScratch::default();
+    c.bench_function("find_matching_template_with_scratch", |b| {
+        b.iter(|| {
+            find_matching_template_with_scratch(
+                black_box(&templates),
+                black_box(&path),
+                45.0,
+                2.0,
+                &mut scratch,
+            )
+            .map(|(template, score)| (template.name.len(), score))
+        })
+    });
+}
+
+fn large_library(c: &mut Criterion) {
+    // 250 variants of each of the eight shapes.
+    let templates = Augmenter::new(Augmentation::default(), 1).expand_templates(&templates(), 249);
+    assert_eq!(templates.len(), 2000);
+    let path = polyline(&[(10., 5.), (190., 20.), (0., 210.), (205., 190.)]);
+
+    let mut scratch = MatchScratch::default();
+    c.bench_function("large_library", |b| {
+        b.iter(|| {
+            find_matching_template_with_scratch(
+                black_box(&templates),
+                black_box(&path),
+                45.0,
+                2.0,
+                &mut scratch,
+            )
+            .map(|(template, score)| (template.name.len(), score))
+        })
+    });
+}
+
+criterion_group!(benches, matching, large_library);
+criterion_main!(benches);
@@ -0,0 +1,220 @@
+//! Diagnostics for template sets. Templates of different gestures that are too similar
+//! cause recognition to flip unpredictably between them, and badly recorded templates
+//! can match other gestures better than their own; both are easier to fix before a set
+//! is shipped.
+
+use crate::{measure_variants, score_for_distance, Template, Variant};
+use euclid::Angle;
+use num_traits::Float;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+impl Template {
+    /// Returns how closely this template matches another, as a score between 0.0 and
+    /// 1.0 on the same scale as [find_matching_template](crate::find_matching_template).
+    /// This template is compared like a drawn path, so every variation allowed by the
+    /// other template's [Invariance](crate::Invariance) is tried. `angle_range` and
+    /// `angle_precision` are in degrees.
+    pub fn similarity(&self, other: &Template, angle_range: f32, angle_precision: f32) -> f32 {
+        let angle_range: f32 = Angle::degrees(angle_range).get();
+        let angle_precision: f32 = Angle::degrees(angle_precision).get();
+        let variants = Variant::all(&self.path, other.invariance);
+        let distance = measure_variants(
+            &variants,
+            other,
+            angle_range,
+            angle_precision,
+            f32::INFINITY,
+        );
+        score_for_distance(distance.map_or(f32::MAX, |(distance, _)| distance))
+    }
+}
+
+/// The similarity of every pair of templates in a set.
+#[derive(Debug, Clone)]
+pub struct SimilarityMatrix {
+    /// The names of the analyzed templates, in their original order.
+    pub names: Vec<String>,
+    /// `scores[i][j]` is the similarity of templates `i` and `j`. [Template::similarity]
+    /// depends on which template it is called on, so both directions are compared and the
+    /// greater score is kept; the matrix is symmetric, and the diagonal is 1.0.
+    pub scores: Vec<Vec<f32>>,
+}
+
+/// A pair of templates with different names that are similar enough to be confused.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Conflict {
+    /// The index of the first template of the pair.
+    pub a: usize,
+    /// The index of the second template of the pair, which is greater than `a`.
+    pub b: usize,
+    /// The similarity of the two templates.
+    pub score: f32,
+}
+
+/// A template that does not resemble the other templates with the same name.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Outlier {
+    pub index: usize,
+    /// The mean similarity of this template to the other templates with the same name.
+    pub class_similarity: f32,
+    /// The most similar template with a different name, if it is more similar than
+    /// every template with the same name.
+    pub closer_to: Option<usize>,
+}
+
+impl SimilarityMatrix {
+    /// Compare every pair of templates. `angle_range` and `angle_precision` are in degrees,
+    /// as for [find_matching_template](crate::find_matching_template).
+    pub fn new(templates: &[Template], angle_range: f32, angle_precision: f32) -> SimilarityMatrix {
+        let mut scores = vec![vec![1.0; templates.len()]; templates.len()];
+        for (i, a) in templates.iter().enumerate() {
+            for (j, b) in templates.iter().enumerate().skip(i + 1) {
+                let score = a
+                    .similarity(b, angle_range, angle_precision)
+                    .max(b.similarity(a, angle_range, angle_precision));
+                scores[i][j] = score;
+                scores[j][i] = score;
+            }
+        }
+        SimilarityMatrix {
+            names: templates.iter().map(|t| t.name.clone()).collect(),
+            scores,
+        }
+    }
+
+    /// Returns every pair of templates with different names whose similarity is at least
+    /// `threshold`, most similar first.
+    pub fn conflicts(&self, threshold: f32) -> Vec<Conflict> {
+        let mut conflicts = vec![];
+        for a in 0..self.names.len() {
+            for b in a + 1..self.names.len() {
+                let score = self.scores[a][b];
+                if self.names[a] != self.names[b] && score >= threshold {
+                    conflicts.push(Conflict { a, b, score });
+                }
+            }
+        }
+        conflicts.sort_by(|x, y| y.score.total_cmp(&x.score));
+        conflicts
+    }
+
+    /// Returns templates that resemble their own class poorly: those whose mean similarity
+    /// to the rest of their class is more than `deviations` standard deviations below the
+    /// class average, and those that are more similar to a template of another class than
+    /// to any template of their own. Classes with a single template are never reported.
+    pub fn outliers(&self, deviations: f32) -> Vec<Outlier> {
+        let count = self.names.len();
+        let class_similarity: Vec<Option<f32>> = (0..count)
+            .map(|i| {
+                let same: Vec<f32> = (0..count)
+                    .filter(|&j| j != i && self.names[j] == self.names[i])
+                    .map(|j| self.scores[i][j])
+                    .collect();
+                if same.is_empty() {
+                    return None;
+                }
+                Some(same.iter().sum::<f32>() / same.len() as f32)
+            })
+            .collect();
+
+        let mut outliers = vec![];
+        for i in 0..count {
+            let Some(similarity) = class_similarity[i] else { continue };
+
+            let members: Vec<f32> = (0..count)
+                .filter(|&j| self.names[j] == self.names[i])
+                .filter_map(|j| class_similarity[j])
+                .collect();
+            let mean = members.iter().sum::<f32>() / members.len() as f32;
+            let variance = members.iter().map(|s| Float::powi(s - mean, 2)).sum::<f32>() /
+                members.len() as f32;
+            let deviant = similarity < mean - deviations * Float::sqrt(variance);
+
+            let best_same = (0..count)
+                .filter(|&j| j != i && self.names[j] == self.names[i])
+                .map(|j| self.scores[i][j])
+                .fold(f32::MIN, f32::max);
+            let closer_to = (0..count)
+                .filter(|&j| self.names[j] != self.names[i])
+                .max_by(|&a, &b| self.scores[i][a].total_cmp(&self.scores[i][b]))
+                .filter(|&j| self.scores[i][j] > best_same);
+
+            if deviant || closer_to.is_some() {
+                outliers.push(Outlier {
+                    index: i,
+                    class_similarity: similarity,
+                    closer_to,
+                });
+            }
+        }
+        outliers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Invariance, Path2D};
+
+    fn template(name: &str, points: &[(f32, f32)]) -> Template {
+        let mut path = Path2D::default();
+        for pair in points.windows(2) {
+            let [(x0, y0), (x1, y1)] = pair else { unreachable!() };
+            for i in 0..10 {
+                let t = i as f32 / 10.0;
+                path.push(x0 + (x1 - x0) * t, y0 + (y1 - y0) * t);
+            }
+        }
+        let &(x, y) = points.last().unwrap();
+        path.push(x, y);
+        Template::new(name.to_owned(), &path).unwrap()
+    }
+
+    #[test]
+    fn conflicts_and_outliers() {
+        let v = [(0., 0.), (100., 200.), (200., 0.)];
+        let caret = [(0., 200.), (100., 0.), (200., 200.)];
+        let z = [(0., 0.), (200., 0.), (0., 200.), (200., 200.)];
+        let templates = [
+            template("v", &v),
+            template("v", &[(0., 0.), (100., 210.), (200., 0.)]),
+            template("v", &[(0., 0.), (95., 190.), (200., 10.)]),
+            // Mislabeled: actually a Z.
+            template("v", &z),
+            template("z", &z),
+            template("z", &[(0., 0.), (210., 0.), (0., 190.), (200., 200.)]),
+            template("caret", &caret),
+        ];
+        let matrix = SimilarityMatrix::new(&templates, 45.0, 2.0);
+        assert_eq!(matrix.scores[1][0], matrix.scores[0][1]);
+        let (forward, backward) = (
+            templates[3].similarity(&templates[6], 45.0, 2.0),
+            templates[6].similarity(&templates[3], 45.0, 2.0),
+        );
+        assert_eq!(matrix.scores[6][3], forward.max(backward));
+
+        let conflicts = matrix.conflicts(0.95);
+        assert!(conflicts.iter().any(|c| (c.a, c.b) == (3, 4)));
+        assert!(!conflicts.iter().any(|c| c.a == 0 || c.a == 1));
+
+        // The mislabeled template is reported, as is the Z it is closest to.
+        let outliers = matrix.outliers(1.0);
+        assert!(outliers.iter().any(|o| o.index == 3 && o.closer_to == Some(4)));
+        assert!(outliers.iter().all(|o| o.index == 3 || o.index == 4));
+    }
+
+    #[test]
+    fn conflicts_allow_for_invariance() {
+        // An S and a Z are mirror images of each other.
+        let s = [(200., 0.), (0., 0.), (0., 100.), (200., 100.), (200., 200.), (0., 200.)];
+        let z = [(0., 0.), (200., 0.), (200., 100.), (0., 100.), (0., 200.), (200., 200.)];
+        let mut templates = [template("s", &s), template("z", &z)];
+        assert!(SimilarityMatrix::new(&templates, 45.0, 2.0).conflicts(0.95).is_empty());
+
+        templates[0].invariance = Invariance { mirror_horizontal: true, ..Invariance::default() };
+        let conflicts = SimilarityMatrix::new(&templates, 45.0, 2.0).conflicts(0.95);
+        assert_eq!(conflicts.iter().map(|c| (c.a, c.b)).collect::<Vec<_>>(), [(0, 1)]);
+    }
+}
@@ -0,0 +1,293 @@
+//! Synthetic variations of recorded paths, for expanding small template sets.
+//!
+//! The deterministic transformations are available as methods on [Path2D]; an [Augmenter]
+//! combines them with randomly drawn parameters to produce plausible new samples of the
+//! same gesture. Randomness comes from a seeded generator, so the same seed and
+//! [Augmentation] settings always produce the same variants.
+
+use crate::{Coord, Path2D, Template};
+use euclid::default::Point2D;
+use num_traits::Float;
+use alloc::vec;
+use alloc::vec::Vec;
+
+impl<T: Coord> Path2D<T> {
+    /// Returns a copy of this path rotated by `radians` around its centroid.
+    pub fn rotated(&self, radians: T) -> Path2D<T> {
+        self.rotate_by(radians)
+    }
+
+    /// Returns a copy of this path scaled by independent horizontal and vertical
+    /// factors around its centroid.
+    pub fn scaled(&self, sx: T, sy: T) -> Path2D<T> {
+        self.transform_around_centroid(|x, y| (x * sx, y * sy))
+    }
+
+    /// Returns a copy of this path sheared around its centroid. Each point moves
+    /// horizontally by `shx` times its vertical offset, and vertically by `shy` times
+    /// its horizontal offset.
+    pub fn sheared(&self, shx: T, shy: T) -> Path2D<T> {
+        self.transform_around_centroid(|x, y| (x + shx * y, y + shy * x))
+    }
+
+    fn transform_around_centroid(
+        &self,
+        f: impl Fn(T, T) -> (T, T),
+    ) -> Path2D<T> {
+        if self.points.is_empty() {
+            return self.clone();
+        }
+        let centroid = self.centroid();
+        Path2D {
+            points: self.points
+                .iter()
+                .map(|point| {
+                    let (x, y) = f(point.x - centroid.x, point.y - centroid.y);
+                    Point2D::new(x + centroid.x, y + centroid.y)
+                })
+                .collect()
+        }
+    }
+}
+
+/// Limits for each random transformation applied by an [Augmenter]. A value of zero
+/// disables the corresponding transformation.
+#[derive(Debug, Clone, Copy)]
+pub struct Augmentation {
+    /// Maximum rotation in degrees, in either direction.
+    pub rotation: f32,
+    /// Maximum relative change of each axis' scale; 0.2 scales each axis by 0.8 to 1.2.
+    pub scale: f32,
+    /// Maximum shear factor along each axis.
+    pub shear: f32,
+    /// Standard deviation of the Gaussian noise added to each point, relative to the
+    /// length of the path's bounding box diagonal.
+    pub jitter: f32,
+    /// Probability that any point other than the first and last is removed.
+    pub dropout: f32,
+    /// Maximum relative variation of the spacing between consecutive points when the
+    /// path is resampled, simulating changes in drawing speed.
+    pub speed: f32,
+}
+
+impl Default for Augmentation {
+    fn default() -> Self {
+        Augmentation {
+            rotation: 15.0,
+            scale: 0.2,
+            shear: 0.1,
+            jitter: 0.01,
+            dropout: 0.05,
+            speed: 0.5,
+        }
+    }
+}
+
+/// Generates random variants of paths according to an [Augmentation].
+pub struct Augmenter {
+    /// The limits of the transformations applied to each variant.
+    pub config: Augmentation,
+    rng: Rng,
+}
+
+impl Augmenter {
+    /// Create a new augmenter. Two augmenters created with the same configuration and
+    /// seed produce identical sequences of variants.
+    pub fn new(config: Augmentation, seed: u64) -> Augmenter {
+        Augmenter {
+            config,
+            rng: Rng(seed),
+        }
+    }
+
+    /// Returns a single random variant of the provided path.
+    pub fn augment(&mut self, path: &Path2D) -> Path2D {
+        if path.points.len() < 2 {
+            return path.clone();
+        }
+        let config = self.config;
+        let path = self.resample_speed(path, config.speed);
+        let path = self.drop_points(&path, config.dropout);
+        let path = path.scaled(
+            1.0 + self.rng.symmetric(config.scale),
+            1.0 + self.rng.symmetric(config.scale),
+        );
+        let path = path.sheared(self.rng.symmetric(config.shear), self.rng.symmetric(config.shear));
+        let path = path.rotated(self.rng.symmetric(config.rotation).to_radians());
+        self.jitter(&path, config.jitter)
+    }
+
+    /// Returns `count` random variants of the provided path.
+    pub fn variants(&mut self, path: &Path2D, count: usize) -> Vec<Path2D> {
+        (0..count).map(|_| self.augment(path)).collect()
+    }
+
+    /// Returns the provided templates followed by `count` normalized variants of each.
+    pub fn expand_templates(&mut self, templates: &[Template], count: usize) -> Vec<Template> {
+        let mut expanded = vec![];
+        for template in templates {
+            if let Ok(original) = Template::new_from_template(
+                template.name.clone(),
+                template.path.clone(),
+            ) {
+                expanded.push(original);
+            }
+            for variant in self.variants(&template.path, count) {
+                if let Ok(variant) = Template::new(template.name.clone(), &variant) {
+                    expanded.push(variant);
+                }
+            }
+        }
+        expanded
+    }
+
+    /// Place points along the path at randomly varying arc-length intervals, in the
+    /// style of stochastic gesture path resampling.
+    fn resample_speed(&mut self, path: &Path2D, speed: f32) -> Path2D {
+        if speed <= 0.0 {
+            return path.clone();
+        }
+        let count = path.points.len().max(2);
+        let intervals: Vec<f32> = (1..count)
+            .map(|_| (1.0 + self.rng.symmetric(speed)).max(0.01))
+            .collect();
+        let total_interval: f32 = intervals.iter().sum();
+        let length = path.length();
+
+        let mut resampled = Path2D {
+            points: vec![path.points[0]],
+        };
+        let mut segments = path.points.windows(2);
+        let mut segment = segments.next();
+        let mut segment_start = 0.0;
+        let mut target = 0.0;
+        for &interval in &intervals[..intervals.len() - 1] {
+            target += interval / total_interval * length;
+            while let Some(&[a, b]) = segment {
+                let segment_length = a.distance_to(b);
+                if segment_start + segment_length >= target {
+                    let t = if segment_length > 0.0 {
+                        (target - segment_start) / segment_length
+                    } else {
+                        0.0
+                    };
+                    resampled.points.push(a.lerp(b, t));
+                    break;
+                }
+                segment_start += segment_length;
+                segment = segments.next();
+            }
+        }
+        resampled.points.push(path.points[path.points.len() - 1]);
+        resampled
+    }
+
+    fn drop_points(&mut self, path: &Path2D, probability: f32) -> Path2D {
+        let last = path.points.len() - 1;
+        let points: Vec<_> = path.points
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| i == 0 || i == last || self.rng.next_f32() >= probability)
+            .map(|(_, point)| *point)
+            .collect();
+        Path2D { points }
+    }
+
+    fn jitter(&mut self, path: &Path2D, jitter: f32) -> Path2D {
+        if jitter <= 0.0 {
+            return path.clone();
+        }
+        let bounds = path.bounding_rect();
+        let sigma = jitter * Float::hypot(bounds.width(), bounds.height());
+        Path2D {
+            points: path.points
+                .iter()
+                .map(|point| {
+                    Point2D::new(
+                        point.x + self.rng.gaussian() * sigma,
+                        point.y + self.rng.gaussian() * sigma,
+                    )
+                })
+                .collect()
+        }
+    }
+}
+
+/// A small SplitMix64 generator. Implemented locally so that seeded results stay
+/// stable regardless of dependency versions.
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    /// A uniformly distributed value in [0, 1).
+    fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// A uniformly distributed value in [-max, max).
+    fn symmetric(&mut self, max: f32) -> f32 {
+        (self.next_f32() * 2.0 - 1.0) * max
+    }
+
+    /// A normally distributed value with mean 0 and standard deviation 1.
+    fn gaussian(&mut self) -> f32 {
+        let u1 = 1.0 - self.next_f32();
+        let u2 = self.next_f32();
+        Float::sqrt(-2.0 * Float::ln(u1)) * Float::cos(core::f32::consts::TAU * u2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square() -> Path2D {
+        let mut path = Path2D::default();
+        let corners = [(0., 0.), (100., 0.), (100., 100.), (0., 100.), (0., 0.)];
+        for pair in corners.windows(2) {
+            let [(x0, y0), (x1, y1)] = pair else { unreachable!() };
+            for i in 0..10 {
+                let t = i as f32 / 10.0;
+                path.push(x0 + (x1 - x0) * t, y0 + (y1 - y0) * t);
+            }
+        }
+        path.push(0., 0.);
+        path
+    }
+
+    #[test]
+    fn seeded_variants_are_reproducible() {
+        let path = square();
+        let a = Augmenter::new(Augmentation::default(), 7).variants(&path, 3);
+        let b = Augmenter::new(Augmentation::default(), 7).variants(&path, 3);
+        let c = Augmenter::new(Augmentation::default(), 8).variants(&path, 3);
+        let points = |paths: &[Path2D]| paths.iter().map(|p| p.points()).collect::<Vec<_>>();
+        assert_eq!(points(&a), points(&b));
+        assert_ne!(points(&a), points(&c));
+        assert_ne!(points(&a)[0], path.points());
+    }
+
+    #[test]
+    fn speed_resampling_preserves_endpoints() {
+        let path = square();
+        let config = Augmentation {
+            rotation: 0.0,
+            scale: 0.0,
+            shear: 0.0,
+            jitter: 0.0,
+            dropout: 0.0,
+            speed: 0.8,
+        };
+        let variant = Augmenter::new(config, 1).augment(&path);
+        assert_eq!(variant.points().len(), path.points().len());
+        assert_eq!(variant.points().first(), path.points().first());
+        assert_eq!(variant.points().last(), path.points().last());
+    }
+}
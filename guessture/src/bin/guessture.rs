@@ -0,0 +1,515 @@
+//! Command-line tool for building, evaluating and converting gesture files without
+//! running an interactive trainer.
+
+use clap::{Parser, Subcommand, ValueEnum};
+use guessture::augment::{Augmentation, Augmenter};
+use guessture::binary::Encoding;
+use guessture::eval::{self, Dataset, Report};
+use guessture::format::GestureFile;
+use guessture::xml::{Format, XmlGesture};
+use guessture::analysis::SimilarityMatrix;
+use guessture::{svg, Path2D, Template};
+use std::error::Error;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::process::ExitCode;
+
+#[derive(Parser)]
+#[command(name = "guessture", version, about = "Build, evaluate and convert gesture template files")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Build a template file from a directory of recorded samples.
+    ///
+    /// The template file is written as JSON, or in the binary format if its name ends
+    /// with `.gestures.bin`. Samples may be JSON arrays of `[x, y]` points, CSV files with `x,y` rows, SVG
+    /// documents, or $1/$N dataset XML files. Each sample is labeled by the name of the
+    /// directory containing it, or by its file name without a trailing number if it is
+    /// at the top level.
+    Build {
+        /// Directory containing the raw samples.
+        samples: PathBuf,
+        /// Path of the `.gestures` or `.gestures.bin` file to write.
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Number of synthetic variants to add for every sample.
+        #[arg(long, default_value_t = 0)]
+        augment: usize,
+        /// Seed for generating synthetic variants.
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+    },
+    /// Recognize a labeled test set with a template file and report the results.
+    Evaluate {
+        /// The `.gestures` template file to evaluate.
+        templates: PathBuf,
+        /// A directory of labeled samples, or a single sample file.
+        test_set: PathBuf,
+        #[command(flatten)]
+        matching: MatchingArgs,
+        /// Format of the report.
+        #[arg(long, value_enum, default_value_t = ReportFormat::Text)]
+        format: ReportFormat,
+    },
+    /// Report pairs of templates that are similar enough to be confused, and templates
+    /// that do not resemble the rest of their class.
+    Similarity {
+        /// The `.gestures` template file to analyze.
+        templates: PathBuf,
+        #[command(flatten)]
+        matching: MatchingArgs,
+        /// Report differently-named pairs whose similarity is at least this score.
+        #[arg(long, default_value_t = 0.85)]
+        threshold: f32,
+        /// Report templates whose similarity to their class is this many standard
+        /// deviations below the class average.
+        #[arg(long, default_value_t = 2.0)]
+        deviations: f32,
+        /// Print the similarity of every pair of templates.
+        #[arg(long)]
+        all: bool,
+    },
+    /// Convert between template files and sample formats.
+    ///
+    /// The output format is chosen by the output file's extension (`gestures`,
+    /// `gestures.bin`, `json`, `csv`, `svg` or `xml`). Any other output path is treated
+    /// as a directory, and every input gesture is written to `<output>/<label>/<n>.<ext>`
+    /// using `--to`. Converting JSON templates to the binary format and back is lossless
+    /// unless `--quantize` is used.
+    Convert {
+        /// A `.gestures` or `.gestures.bin` file, a single sample file, or a directory of
+        /// samples.
+        input: PathBuf,
+        output: PathBuf,
+        /// Sample format to use when writing to a directory.
+        #[arg(long, value_enum, default_value_t = SampleFormat::Svg)]
+        to: SampleFormat,
+        /// Store points as 16-bit integers when writing a `.gestures.bin` file, halving
+        /// its size.
+        #[arg(long)]
+        quantize: bool,
+    },
+}
+
+#[derive(clap::Args)]
+struct MatchingArgs {
+    /// Range of rotation in degrees in which paths are compared against templates.
+    #[arg(long, default_value_t = 45.0)]
+    angle_range: f32,
+    /// Precision in degrees at which rotations are attempted.
+    #[arg(long, default_value_t = 2.0)]
+    angle_precision: f32,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ReportFormat {
+    Text,
+    Json,
+    Csv,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum SampleFormat {
+    Json,
+    Csv,
+    Svg,
+    Xml,
+}
+
+impl SampleFormat {
+    fn from_path(path: &Path) -> Option<SampleFormat> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "json" => Some(SampleFormat::Json),
+            "csv" => Some(SampleFormat::Csv),
+            "svg" => Some(SampleFormat::Svg),
+            "xml" => Some(SampleFormat::Xml),
+            _ => None,
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            SampleFormat::Json => "json",
+            SampleFormat::Csv => "csv",
+            SampleFormat::Svg => "svg",
+            SampleFormat::Xml => "xml",
+        }
+    }
+}
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let result = match cli.command {
+        Command::Build { samples, output, augment, seed } =>
+            build(&samples, &output, augment, seed),
+        Command::Evaluate { templates, test_set, matching, format } =>
+            evaluate(&templates, &test_set, &matching, format),
+        Command::Similarity { templates, matching, threshold, deviations, all } =>
+            similarity(&templates, &matching, threshold, deviations, all),
+        Command::Convert { input, output, to, quantize } =>
+            convert(&input, &output, to, quantize),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn build(samples: &Path, output: &Path, augment: usize, seed: u64) -> Result<()> {
+    let (dataset, files) = read_samples(samples)?;
+    let mut augmenter = Augmenter::new(Augmentation::default(), seed);
+    let mut templates = vec![];
+    for (sample, file) in dataset.samples.iter().zip(&files) {
+        templates.push(sample_template(file, &sample.label, &sample.path)?);
+        for variant in augmenter.variants(&sample.path, augment) {
+            templates.push(sample_template(file, &sample.label, &variant)?);
+        }
+    }
+    write_templates(output, &templates, Encoding::F32)?;
+    eprintln!("wrote {} templates to {}", templates.len(), output.display());
+    Ok(())
+}
+
+fn evaluate(
+    templates: &Path,
+    test_set: &Path,
+    matching: &MatchingArgs,
+    format: ReportFormat,
+) -> Result<()> {
+    let templates = read_templates(templates)?;
+    let (dataset, _) = read_samples(test_set)?;
+    let config = eval::Config {
+        angle_range: matching.angle_range,
+        angle_precision: matching.angle_precision,
+        ..eval::Config::default()
+    };
+    let report = eval::evaluate(&templates, &dataset.samples, &config);
+    match format {
+        ReportFormat::Text => print_report(&report),
+        ReportFormat::Json => println!("{}", report.to_json()?),
+        ReportFormat::Csv => print!("{}", report.results_csv()),
+    }
+    Ok(())
+}
+
+fn print_report(report: &Report) {
+    println!("samples:  {}", report.results.len());
+    println!("accuracy: {:.3}", report.accuracy());
+    println!();
+
+    let width = report.classes.iter().map(|c| c.len()).max().unwrap_or(0).max(6) + 4;
+    println!("{:width$}  precision  recall  support", "class");
+    for metrics in report.class_metrics() {
+        println!(
+            "{:width$}  {:>9.3}  {:>6.3}  {:>7}",
+            metrics.label,
+            metrics.precision,
+            metrics.recall,
+            metrics.support,
+        );
+    }
+    println!();
+
+    println!("confusion matrix (rows: actual, columns: predicted)");
+    print!("{:width$}", "");
+    for index in 0..report.classes.len() {
+        print!(" {:>5}", index);
+    }
+    println!(" {:>5}", "none");
+    for (index, row) in report.confusion_matrix().iter().enumerate() {
+        print!("{:width$}", format!("{} {}", index, report.classes[index]));
+        for count in row {
+            print!(" {:>5}", count);
+        }
+        println!();
+    }
+    println!();
+
+    let (correct, incorrect) = report.score_distributions();
+    for (name, summary) in [("correct", correct), ("incorrect", incorrect)] {
+        println!(
+            "{} scores: n={} min={:.3} median={:.3} mean={:.3} max={:.3}",
+            name,
+            summary.count,
+            summary.min,
+            summary.median,
+            summary.mean,
+            summary.max,
+        );
+    }
+}
+
+fn similarity(
+    templates: &Path,
+    matching: &MatchingArgs,
+    threshold: f32,
+    deviations: f32,
+    all: bool,
+) -> Result<()> {
+    let templates = read_templates(templates)?;
+    let matrix = SimilarityMatrix::new(
+        &templates,
+        matching.angle_range,
+        matching.angle_precision,
+    );
+    let describe = |index: usize| format!("{} {:?}", index, matrix.names[index]);
+
+    if all {
+        println!("all pairs");
+        let mut pairs = vec![];
+        for a in 0..templates.len() {
+            for b in a + 1..templates.len() {
+                pairs.push((matrix.scores[a][b], a, b));
+            }
+        }
+        pairs.sort_by(|x, y| y.0.total_cmp(&x.0));
+        for (score, a, b) in pairs {
+            println!("  {:.3}  {}  {}", score, describe(a), describe(b));
+        }
+        println!();
+    }
+
+    let conflicts = matrix.conflicts(threshold);
+    println!("{} conflicting pairs (similarity >= {})", conflicts.len(), threshold);
+    for conflict in conflicts {
+        println!("  {:.3}  {}  {}", conflict.score, describe(conflict.a), describe(conflict.b));
+    }
+    println!();
+
+    let outliers = matrix.outliers(deviations);
+    println!("{} outliers", outliers.len());
+    for outlier in outliers {
+        print!("  {}  class similarity {:.3}", describe(outlier.index), outlier.class_similarity);
+        if let Some(other) = outlier.closer_to {
+            print!(", closer to {} ({:.3})", describe(other), matrix.scores[outlier.index][other]);
+        }
+        println!();
+    }
+    Ok(())
+}
+
+fn convert(input: &Path, output: &Path, to: SampleFormat, quantize: bool) -> Result<()> {
+    let is_template_file = |path: &Path| {
+        is_binary_template_file(path) || path.extension().is_some_and(|ext| ext == "gestures")
+    };
+
+    if is_template_file(output) {
+        let templates = if is_template_file(input) {
+            read_templates(input)?
+        } else {
+            let (dataset, files) = read_samples(input)?;
+            dataset.samples
+                .iter()
+                .zip(&files)
+                .map(|(sample, file)| sample_template(file, &sample.label, &sample.path))
+                .collect::<Result<_>>()?
+        };
+        let encoding = if quantize { Encoding::I16 } else { Encoding::F32 };
+        return write_templates(output, &templates, encoding);
+    }
+
+    let gestures: Vec<(String, Path2D)> = if is_template_file(input) {
+        read_templates(input)?
+            .into_iter()
+            .map(|template| (template.name, template.path))
+            .collect()
+    } else {
+        read_samples(input)?
+            .0
+            .samples
+            .into_iter()
+            .map(|sample| (sample.label, sample.path))
+            .collect()
+    };
+
+    if let Some(format) = SampleFormat::from_path(output) {
+        let [(label, path)] = &gestures[..] else {
+            return Err(format!(
+                "{} gestures cannot be written to a single file; use a directory",
+                gestures.len(),
+            ).into());
+        };
+        return write_sample(output, format, label, path);
+    }
+
+    let mut counts = std::collections::HashMap::new();
+    for (label, path) in &gestures {
+        let count = counts.entry(label.clone()).or_insert(0);
+        *count += 1;
+        let directory = output.join(label_directory(label)?);
+        fs::create_dir_all(&directory)?;
+        let file = directory.join(format!("{}.{}", count, to.extension()));
+        write_sample(&file, to, label, path)?;
+    }
+    eprintln!("wrote {} gestures to {}", gestures.len(), output.display());
+    Ok(())
+}
+
+/// Returns `label` as a directory name, or an error if joining it to another directory
+/// could escape that directory, as labels read from files can contain anything.
+fn label_directory(label: &str) -> Result<&Path> {
+    let path = Path::new(label);
+    match path.components().collect::<Vec<_>>()[..] {
+        [Component::Normal(_)] => Ok(path),
+        _ => Err(format!("label {:?} cannot be used as a directory name", label).into()),
+    }
+}
+
+fn is_binary_template_file(path: &Path) -> bool {
+    path.to_string_lossy().ends_with(".gestures.bin")
+}
+
+fn read_templates(path: &Path) -> Result<Vec<Template>> {
+    let file = if is_binary_template_file(path) {
+        GestureFile::from_binary(&fs::read(path)?)?
+    } else {
+        GestureFile::from_json(&fs::read_to_string(path)?)?
+    };
+    Ok(file.to_templates()?)
+}
+
+/// Write templates as JSON, or in the binary format with `encoding` if the file name ends
+/// with `.gestures.bin`.
+fn write_templates(path: &Path, templates: &[Template], encoding: Encoding) -> Result<()> {
+    let file = GestureFile::from_templates(templates);
+    if is_binary_template_file(path) {
+        fs::write(path, file.to_binary(encoding)?)?;
+    } else {
+        fs::write(path, file.to_json()?)?;
+    }
+    Ok(())
+}
+
+/// Normalize a sample read from `file` into a template. Returns an error naming the file
+/// if the sample is empty, or if it is a straight line: rotating a line to its indicative
+/// angle leaves it with no height, so it cannot be scaled to a square.
+fn sample_template(file: &Path, label: &str, path: &Path2D) -> Result<Template> {
+    let template = Template::new(label.to_owned(), path)
+        .map_err(|_| format!("{}: the {:?} sample has no points", file.display(), label))?;
+    if !template.path.points().iter().all(|(x, y)| x.is_finite() && y.is_finite()) {
+        return Err(format!(
+            "{}: the {:?} sample is too straight to be normalized into a template",
+            file.display(),
+            label,
+        ).into());
+    }
+    Ok(template)
+}
+
+/// Read a single sample file, or every sample file in a directory tree, in sorted order.
+/// Also returns the file each sample was read from.
+fn read_samples(path: &Path) -> Result<(Dataset, Vec<PathBuf>)> {
+    let mut files = vec![];
+    if path.is_dir() {
+        collect_files(path, &mut files)?;
+        files.sort();
+    } else {
+        files.push(path.to_owned());
+    }
+
+    let mut dataset = Dataset::default();
+    let mut sample_files = vec![];
+    for file in files {
+        let Some(format) = SampleFormat::from_path(&file) else { continue };
+        let (label, user, sample) = read_sample(&file, format)
+            .map_err(|err| format!("{}: {}", file.display(), err))?;
+        let label = label.unwrap_or_else(|| label_for(path, &file));
+        dataset.push(label, user, sample);
+        sample_files.push(file);
+    }
+    Ok((dataset, sample_files))
+}
+
+/// Returns the label for a sample file: the name of its parent directory if it is
+/// nested below `root`, or its file name without any trailing number otherwise.
+fn label_for(root: &Path, file: &Path) -> String {
+    let parent = file.parent().filter(|parent| root.is_dir() && *parent != root);
+    if let Some(name) = parent.and_then(|parent| parent.file_name()) {
+        return name.to_string_lossy().into_owned();
+    }
+    let stem = file.file_stem().unwrap_or_default().to_string_lossy();
+    stem.trim_end_matches(|c: char| c.is_ascii_digit())
+        .trim_end_matches(['~', '-', '_'])
+        .to_owned()
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Returns the label and user recorded in the sample itself, if any, and its path.
+fn read_sample(file: &Path, format: SampleFormat) -> Result<(Option<String>, String, Path2D)> {
+    let contents = fs::read_to_string(file)?;
+    let mut path = Path2D::default();
+    let mut push = |x, y| {
+        if path.is_new_point(x, y) {
+            path.push(x, y);
+        }
+    };
+    match format {
+        SampleFormat::Json => {
+            let points: Vec<(f32, f32)> = serde_json::from_str(&contents)?;
+            for (x, y) in points {
+                push(x, y);
+            }
+        }
+        SampleFormat::Csv => {
+            for line in contents.lines() {
+                let mut fields = line.split(',').map(|field| field.trim().parse::<f32>());
+                // Lines that do not start with two numbers, such as headers, are skipped.
+                if let (Some(Ok(x)), Some(Ok(y))) = (fields.next(), fields.next()) {
+                    push(x, y);
+                }
+            }
+        }
+        SampleFormat::Svg => {
+            for svg_path in svg::paths_from_document(&contents)? {
+                for (x, y) in svg_path.points() {
+                    push(x, y);
+                }
+            }
+        }
+        SampleFormat::Xml => {
+            let gesture = XmlGesture::parse(&contents)?;
+            return Ok((Some(gesture.label().to_owned()), gesture.subject.clone(), gesture.path()));
+        }
+    }
+    Ok((None, String::new(), path))
+}
+
+fn write_sample(file: &Path, format: SampleFormat, label: &str, path: &Path2D) -> Result<()> {
+    let contents = match format {
+        SampleFormat::Json => serde_json::to_string(&path.points())?,
+        SampleFormat::Csv => {
+            let mut csv = "x,y\n".to_owned();
+            for (x, y) in path.points() {
+                csv.push_str(&format!("{},{}\n", x, y));
+            }
+            csv
+        }
+        SampleFormat::Svg => svg::path_to_svg(path),
+        SampleFormat::Xml => {
+            XmlGesture::from_path(label.to_owned(), String::new(), path).to_xml(Format::Unistroke)
+        }
+    };
+    fs::write(file, contents)?;
+    Ok(())
+}
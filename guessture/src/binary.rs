@@ -0,0 +1,304 @@
+//! A compact, versioned binary encoding of template sets, for template libraries that are
+//! too large to ship or parse quickly as `.gestures` JSON.
+//!
+//! All values are little-endian. A file starts with a header:
+//!
+//! | bytes | contents                                       |
+//! |-------|------------------------------------------------|
+//! | 4     | the magic number `GSTR`                        |
+//! | 2     | the format version, currently 1                |
+//! | 1     | the point [Encoding]: 0 for `f32`, 1 for `i16` |
+//! | 1     | reserved, always 0                             |
+//! | 4     | the number of templates                        |
+//!
+//! followed by each template in turn:
+//!
+//! | bytes | contents                                                                |
+//! |-------|-------------------------------------------------------------------------|
+//! | 4     | the length of the name in bytes                                         |
+//! | n     | the name, as UTF-8                                                      |
+//! | 1     | [Invariance] flags: direction, start point, horizontal, vertical mirror |
+//! | 4     | the number of points                                                    |
+//! | 4     | `i16` encoding only: the `f32` scale that multiplies every coordinate   |
+//! | n     | the points, as `(x, y)` pairs of `f32` or `i16`                         |
+//!
+//! The `f32` encoding reproduces templates exactly. The `i16` encoding halves their size by
+//! quantizing each template's coordinates to 65535 steps across its extent, which is far
+//! below the precision that matching can distinguish.
+
+use crate::{Invariance, Path2D, PathCoord, Template};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use num_traits::Float;
+
+/// The bytes that every binary template file starts with.
+pub const MAGIC: [u8; 4] = *b"GSTR";
+
+/// The version of the format written by [encode]. Files with a newer version are rejected.
+pub const VERSION: u16 = 1;
+
+/// How point coordinates are stored.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Encoding {
+    /// Full-precision floats, which preserve templates exactly.
+    #[default]
+    F32,
+    /// 16-bit integers scaled to each template's extent.
+    I16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// The data does not start with [MAGIC].
+    NotATemplateFile,
+    /// The data was written by a newer version of the format.
+    UnsupportedVersion(u16),
+    /// The header names an unknown point encoding.
+    UnknownEncoding(u8),
+    /// The data ended in the middle of a template.
+    Truncated,
+    /// A template name was not valid UTF-8.
+    InvalidName,
+    /// A template has no points.
+    EmptyTemplate,
+    /// There is data after the last template.
+    TrailingData,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::NotATemplateFile => write!(f, "not a binary template file"),
+            Error::UnsupportedVersion(version) => {
+                write!(f, "unsupported format version {}", version)
+            }
+            Error::UnknownEncoding(encoding) => write!(f, "unknown point encoding {}", encoding),
+            Error::Truncated => write!(f, "unexpected end of data"),
+            Error::InvalidName => write!(f, "template name is not valid UTF-8"),
+            Error::EmptyTemplate => write!(f, "template has no points"),
+            Error::TrailingData => write!(f, "unexpected data after the last template"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+impl Encoding {
+    fn tag(self) -> u8 {
+        match self {
+            Encoding::F32 => 0,
+            Encoding::I16 => 1,
+        }
+    }
+
+    fn from_tag(tag: u8) -> Result<Encoding, Error> {
+        match tag {
+            0 => Ok(Encoding::F32),
+            1 => Ok(Encoding::I16),
+            _ => Err(Error::UnknownEncoding(tag)),
+        }
+    }
+}
+
+const DIRECTION: u8 = 1 << 0;
+const START_POINT: u8 = 1 << 1;
+const MIRROR_HORIZONTAL: u8 = 1 << 2;
+const MIRROR_VERTICAL: u8 = 1 << 3;
+
+fn invariance_flags(invariance: Invariance) -> u8 {
+    [
+        (invariance.direction, DIRECTION),
+        (invariance.start_point, START_POINT),
+        (invariance.mirror_horizontal, MIRROR_HORIZONTAL),
+        (invariance.mirror_vertical, MIRROR_VERTICAL),
+    ]
+    .iter()
+    .filter(|(set, _)| *set)
+    .fold(0, |flags, (_, flag)| flags | flag)
+}
+
+fn invariance_from_flags(flags: u8) -> Invariance {
+    Invariance {
+        direction: flags & DIRECTION != 0,
+        start_point: flags & START_POINT != 0,
+        mirror_horizontal: flags & MIRROR_HORIZONTAL != 0,
+        mirror_vertical: flags & MIRROR_VERTICAL != 0,
+    }
+}
+
+/// Encode a set of templates, storing their points with `encoding`.
+pub fn encode(templates: &[Template], encoding: Encoding) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&MAGIC);
+    bytes.extend_from_slice(&VERSION.to_le_bytes());
+    bytes.extend_from_slice(&[encoding.tag(), 0]);
+    bytes.extend_from_slice(&(templates.len() as u32).to_le_bytes());
+
+    for template in templates {
+        bytes.extend_from_slice(&(template.name.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(template.name.as_bytes());
+        bytes.push(invariance_flags(template.invariance));
+        let points = &template.path.points;
+        bytes.extend_from_slice(&(points.len() as u32).to_le_bytes());
+        match encoding {
+            Encoding::F32 => {
+                for point in points {
+                    bytes.extend_from_slice(&point.x.to_le_bytes());
+                    bytes.extend_from_slice(&point.y.to_le_bytes());
+                }
+            }
+            Encoding::I16 => {
+                let extent = points
+                    .iter()
+                    .flat_map(|p| [p.x.abs(), p.y.abs()])
+                    .fold(0.0, PathCoord::max);
+                let scale = if extent > 0.0 { extent / i16::MAX as PathCoord } else { 1.0 };
+                bytes.extend_from_slice(&scale.to_le_bytes());
+                for point in points {
+                    for coord in [point.x, point.y] {
+                        let quantized = Float::round(coord / scale) as i16;
+                        bytes.extend_from_slice(&quantized.to_le_bytes());
+                    }
+                }
+            }
+        }
+    }
+    bytes
+}
+
+/// Reads values from the front of a byte slice.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], Error> {
+        let bytes = self.bytes(N)?;
+        Ok(bytes.try_into().unwrap())
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if self.0.len() < len {
+            return Err(Error::Truncated);
+        }
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> Result<usize, Error> {
+        Ok(u32::from_le_bytes(self.take()?) as usize)
+    }
+
+    fn f32(&mut self) -> Result<PathCoord, Error> {
+        Ok(PathCoord::from_le_bytes(self.take()?))
+    }
+
+    fn i16(&mut self) -> Result<i16, Error> {
+        Ok(i16::from_le_bytes(self.take()?))
+    }
+}
+
+/// Decode a set of templates previously produced by [encode].
+pub fn decode(bytes: &[u8]) -> Result<Vec<Template>, Error> {
+    let mut reader = Reader(bytes);
+    if reader.take::<4>().ok() != Some(MAGIC) {
+        return Err(Error::NotATemplateFile);
+    }
+    let version = u16::from_le_bytes(reader.take()?);
+    if version > VERSION {
+        return Err(Error::UnsupportedVersion(version));
+    }
+    let [encoding, _reserved] = reader.take()?;
+    let encoding = Encoding::from_tag(encoding)?;
+    let count = reader.u32()?;
+
+    // Every template takes at least nine bytes, so a corrupt count can't cause a huge
+    // allocation.
+    let mut templates = Vec::with_capacity(count.min(bytes.len() / 9));
+    for _ in 0..count {
+        let len = reader.u32()?;
+        let name = reader.bytes(len)?.to_vec();
+        let name = String::from_utf8(name).map_err(|_| Error::InvalidName)?;
+        let [flags] = reader.take()?;
+        let len = reader.u32()?;
+        let mut path = Path2D::default();
+        match encoding {
+            Encoding::F32 => {
+                for _ in 0..len {
+                    path.push(reader.f32()?, reader.f32()?);
+                }
+            }
+            Encoding::I16 => {
+                let scale = reader.f32()?;
+                for _ in 0..len {
+                    let (x, y) = (reader.i16()?, reader.i16()?);
+                    path.push(x as PathCoord * scale, y as PathCoord * scale);
+                }
+            }
+        }
+        let template =
+            Template::new_from_template(name, path).map_err(|_| Error::EmptyTemplate)?;
+        templates.push(template.with_invariance(invariance_from_flags(flags)));
+    }
+    if !reader.0.is_empty() {
+        return Err(Error::TrailingData);
+    }
+    Ok(templates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::borrow::ToOwned;
+    use alloc::vec;
+
+    fn templates() -> Vec<Template> {
+        let mut path = Path2D::default();
+        for (x, y) in [(0., 0.), (50., 100.), (100., 0.), (60., -20.)] {
+            path.push(x, y);
+        }
+        let vee = Template::new("vee".to_owned(), &path).unwrap();
+        let closed = Template::new("closed ✓".to_owned(), &path).unwrap();
+        let closed = closed.with_invariance(Invariance {
+            start_point: true,
+            mirror_vertical: true,
+            ..Invariance::default()
+        });
+        vec![vee, closed]
+    }
+
+    #[test]
+    fn round_trip() {
+        let templates = templates();
+        let decoded = decode(&encode(&templates, Encoding::F32)).unwrap();
+        assert_eq!(decoded.len(), 2);
+        for (a, b) in templates.iter().zip(&decoded) {
+            assert_eq!((&a.name, a.invariance), (&b.name, b.invariance));
+            assert_eq!(a.path.points(), b.path.points());
+        }
+
+        let quantized = encode(&templates, Encoding::I16);
+        assert!(quantized.len() < encode(&templates, Encoding::F32).len() * 6 / 10);
+        let decoded = decode(&quantized).unwrap();
+        assert_eq!(decoded[1].invariance, templates[1].invariance);
+        for (a, b) in templates[0].path.points.iter().zip(&decoded[0].path.points) {
+            assert!((*a - *b).length() < 0.01);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_data() {
+        let bytes = encode(&templates(), Encoding::I16);
+        assert_eq!(decode(b"{\"templates\":[]}").err(), Some(Error::NotATemplateFile));
+        assert_eq!(decode(&bytes[..bytes.len() - 1]).err(), Some(Error::Truncated));
+
+        let mut newer = bytes.clone();
+        newer[4..6].copy_from_slice(&(VERSION + 1).to_le_bytes());
+        assert_eq!(decode(&newer).err(), Some(Error::UnsupportedVersion(VERSION + 1)));
+
+        let mut extra = bytes;
+        extra.push(0);
+        assert_eq!(decode(&extra).err(), Some(Error::TrailingData));
+    }
+}
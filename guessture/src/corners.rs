@@ -0,0 +1,375 @@
+//! Structural analysis of strokes: simplification to a few significant points, and
+//! detection of the corners that divide a stroke into segments.
+//!
+//! Simplification uses the Ramer–Douglas–Peucker algorithm. Corners are found with
+//! ShortStraw (Wolin et al., SBIM 2008), which resamples the stroke at a fixed spacing
+//! and looks for points where the stroke folds back on itself over a short window.
+//! Corner counts are robust to the drawing variations that the $1 distance is sensitive
+//! to, which makes them useful for telling apart gestures such as squares and circles.
+
+use crate::{find_matching_template_where, Coord, Error, Path2D, PathCoord, Point2D, Template};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// The number of resampled points on either side of a point that make up its straw.
+const STRAW_WINDOW: usize = 3;
+
+/// The resampling spacing, as a fraction of the stroke's bounding box diagonal.
+const SPACING_FRACTION: f64 = 1.0 / 40.0;
+
+/// How straight a stretch of the stroke must be, measured as the ratio of its chord to
+/// its length, to be considered a line.
+const LINE_THRESHOLD: f64 = 0.95;
+
+/// A part of a stroke between two consecutive corners.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StrokeSegment<T = PathCoord> {
+    /// The index of the point at which this segment starts.
+    pub start: usize,
+    /// The index of the point at which this segment ends.
+    pub end: usize,
+    /// The length of the stroke between the start and end points.
+    pub length: T,
+    /// The direction from the start point to the end point, in radians.
+    pub angle: T,
+    /// Whether this segment is close to a straight line.
+    pub straight: bool,
+}
+
+impl<T: Coord> Path2D<T> {
+    /// Returns the indices of the points kept when simplifying this path with the
+    /// Ramer–Douglas–Peucker algorithm. Every discarded point lies within `epsilon` of
+    /// the line between the kept points around it. The first and last points are always
+    /// kept.
+    pub fn simplify(&self, epsilon: T) -> Vec<usize> {
+        let len = self.points.len();
+        if len < 3 {
+            return (0..len).collect();
+        }
+
+        let mut keep = vec![false; len];
+        keep[0] = true;
+        keep[len - 1] = true;
+        let mut pending = vec![(0, len - 1)];
+        while let Some((first, last)) = pending.pop() {
+            let farthest = (first + 1..last)
+                .map(|i| {
+                    let distance = distance_to_line(
+                        self.points[i],
+                        self.points[first],
+                        self.points[last],
+                    );
+                    (i, distance)
+                })
+                .max_by(|a, b| a.1.total_cmp(&b.1));
+            let Some((index, distance)) = farthest else { continue };
+            if distance > epsilon {
+                keep[index] = true;
+                pending.push((first, index));
+                pending.push((index, last));
+            }
+        }
+        (0..len).filter(|&i| keep[i]).collect()
+    }
+
+    /// Returns a copy of this path simplified with the Ramer–Douglas–Peucker algorithm.
+    /// See [Path2D::simplify].
+    pub fn simplified(&self, epsilon: T) -> Path2D<T> {
+        Path2D {
+            points: self.simplify(epsilon).into_iter().map(|i| self.points[i]).collect(),
+        }
+    }
+
+    /// Returns the indices of the corners of this path, including the first and last
+    /// points.
+    pub fn corners(&self) -> Vec<usize> {
+        if self.points.len() < 2 {
+            return (0..self.points.len()).collect();
+        }
+        let diagonal = self.bounding_rect().size().to_vector().length();
+        let spacing = diagonal * T::constant(SPACING_FRACTION);
+        let (resampled, sources) = self.resample_with_spacing(spacing);
+        let mut corners: Vec<usize> = short_straw(&resampled)
+            .into_iter()
+            .map(|i| sources[i])
+            .collect();
+        corners.dedup();
+        if corners.len() < 2 {
+            return vec![0, self.points.len() - 1];
+        }
+        corners
+    }
+
+    /// Returns the segments between consecutive corners of this path.
+    pub fn segments(&self) -> Vec<StrokeSegment<T>> {
+        self.corners()
+            .windows(2)
+            .map(|pair| {
+                let (start, end) = (pair[0], pair[1]);
+                let length = self.length_between(start, end);
+                let chord = self.points[end] - self.points[start];
+                StrokeSegment {
+                    start,
+                    end,
+                    length,
+                    angle: chord.y.atan2(chord.x),
+                    straight: length == T::zero() ||
+                        chord.length() / length > T::constant(LINE_THRESHOLD),
+                }
+            })
+            .collect()
+    }
+
+    fn length_between(&self, start: usize, end: usize) -> T {
+        self.points[start..=end]
+            .windows(2)
+            .map(|pair| pair[0].distance_to(pair[1]))
+            .sum()
+    }
+
+    /// Resample this path so that consecutive points are `spacing` apart. Also returns
+    /// the index of the original point nearest to each resampled point.
+    fn resample_with_spacing(&self, spacing: T) -> (Path2D<T>, Vec<usize>) {
+        let mut resampled = Path2D { points: vec![self.points[0]] };
+        let mut sources = vec![0];
+        if spacing <= T::zero() {
+            return (resampled, sources);
+        }
+
+        let mut previous = self.points[0];
+        let mut accumulated = T::zero();
+        for (i, &point) in self.points.iter().enumerate().skip(1) {
+            let mut d = point.distance_to(previous);
+            while accumulated + d >= spacing && d > T::zero() {
+                let t = (spacing - accumulated) / d;
+                previous = previous.lerp(point, t);
+                resampled.points.push(previous);
+                sources.push(if t < T::constant(0.5) { i - 1 } else { i });
+                d = point.distance_to(previous);
+                accumulated = T::zero();
+            }
+            accumulated += d;
+            previous = point;
+        }
+        if accumulated > T::zero() {
+            resampled.points.push(self.points[self.points.len() - 1]);
+            sources.push(self.points.len() - 1);
+        }
+        (resampled, sources)
+    }
+
+    fn is_line(&self, start: usize, end: usize) -> bool {
+        let length = self.length_between(start, end);
+        let chord = self.points[start].distance_to(self.points[end]);
+        length == T::zero() || chord / length > T::constant(LINE_THRESHOLD)
+    }
+}
+
+fn distance_to_line<T: Coord>(point: Point2D<T>, a: Point2D<T>, b: Point2D<T>) -> T {
+    let line = b - a;
+    let length = line.length();
+    if length == T::zero() {
+        return point.distance_to(a);
+    }
+    line.cross(point - a).abs() / length
+}
+
+/// Find the corners of a path that has been resampled at an even spacing.
+fn short_straw<T: Coord>(path: &Path2D<T>) -> Vec<usize> {
+    let points = &path.points;
+    let len = points.len();
+    if len <= 2 * STRAW_WINDOW {
+        return vec![0, len - 1];
+    }
+
+    let mut straws = vec![T::max_value(); len];
+    for i in STRAW_WINDOW..len - STRAW_WINDOW {
+        straws[i] = points[i - STRAW_WINDOW].distance_to(points[i + STRAW_WINDOW]);
+    }
+    let mut sorted: Vec<_> = straws[STRAW_WINDOW..len - STRAW_WINDOW].to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let threshold = sorted[sorted.len() / 2] * T::constant(0.95);
+
+    let mut corners = vec![0];
+    let mut i = STRAW_WINDOW;
+    while i < len - STRAW_WINDOW {
+        if straws[i] < threshold {
+            let mut local_min = i;
+            while i < len - STRAW_WINDOW && straws[i] < threshold {
+                if straws[i] < straws[local_min] {
+                    local_min = i;
+                }
+                i += 1;
+            }
+            corners.push(local_min);
+        }
+        i += 1;
+    }
+    corners.push(len - 1);
+
+    // Split any stretch between corners that is not a line at its sharpest point, if that
+    // point is sharp enough to be a corner. Smooth curves are never lines, but have no
+    // corners.
+    let mut i = 1;
+    while i < corners.len() {
+        let (first, last) = (corners[i - 1], corners[i]);
+        if !path.is_line(first, last) {
+            let quarter = (last - first) / 4;
+            let sharpest = (first + quarter..last - quarter)
+                .min_by(|&a, &b| straws[a].total_cmp(&straws[b]));
+            let corner = sharpest.filter(|&c| c > first && c < last && straws[c] < threshold);
+            if let Some(corner) = corner {
+                corners.insert(i, corner);
+                continue;
+            }
+        }
+        i += 1;
+    }
+
+    // Drop corners that lie on a line between their neighbours.
+    let mut i = 1;
+    while i + 1 < corners.len() {
+        if path.is_line(corners[i - 1], corners[i + 1]) {
+            corners.remove(i);
+        } else {
+            i += 1;
+        }
+    }
+    corners
+}
+
+/// A set of templates along with the number of corners of each, which
+/// [find_matching_template_with_corners] compares against every path.
+pub struct CornerTemplates<'a, T = PathCoord> {
+    templates: &'a [Template<T>],
+    corners: Vec<usize>,
+}
+
+impl<'a, T: Coord> CornerTemplates<'a, T> {
+    /// Count the corners of every template in a set.
+    pub fn new(templates: &'a [Template<T>]) -> CornerTemplates<'a, T> {
+        CornerTemplates {
+            templates,
+            corners: templates.iter().map(|t| t.path.corners().len()).collect(),
+        }
+    }
+}
+
+/// Like [crate::find_matching_template], but only considers templates whose number of
+/// corners differs from the path's by at most `max_corner_difference`. This rejects matches
+/// that are close in shape but structurally different, such as a rounded square matching a
+/// circle. Corners are counted on the normalized path, like those of the templates.
+pub fn find_matching_template_with_corners<'a, T: Coord>(
+    templates: &CornerTemplates<'a, T>,
+    path: &Path2D<T>,
+    angle_range: T,
+    angle_precision: T,
+    max_corner_difference: usize,
+) -> Result<(&'a Template<T>, T), Error> {
+    if path.points.len() < 2 || path.length() < T::constant(100.0) {
+        return Err(Error::TooShort);
+    }
+    let (normalized, _) = Template::normalize(path);
+    let corners = normalized.corners().len();
+    let include = |index: usize| {
+        templates.corners[index].abs_diff(corners) <= max_corner_difference
+    };
+    let (template, score, _) = find_matching_template_where(
+        templates.templates,
+        path,
+        angle_range,
+        angle_precision,
+        &include,
+    )?;
+    Ok((template, score))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{find_matching_template, Invariance};
+
+    fn polyline(points: &[(f32, f32)]) -> Path2D {
+        let mut path = Path2D::default();
+        for pair in points.windows(2) {
+            let [(x0, y0), (x1, y1)] = pair else { unreachable!() };
+            for i in 0..20 {
+                let t = i as f32 / 20.0;
+                path.push(x0 + (x1 - x0) * t, y0 + (y1 - y0) * t);
+            }
+        }
+        let &(x, y) = points.last().unwrap();
+        path.push(x, y);
+        path
+    }
+
+    fn circle() -> Path2D {
+        let mut path = Path2D::default();
+        for i in 0..=80 {
+            let angle = i as f32 / 80.0 * core::f32::consts::TAU;
+            path.push(100.0 + 100.0 * angle.cos(), 100.0 + 100.0 * angle.sin());
+        }
+        path
+    }
+
+    #[test]
+    fn simplification_and_corners() {
+        let mut wobbly = Path2D::default();
+        for i in 0..50 {
+            wobbly.push(i as f32 * 4.0, if i % 2 == 0 { 0.5 } else { -0.5 });
+        }
+        assert_eq!(wobbly.simplify(1.0), vec![0, 49]);
+        let corner = polyline(&[(0., 0.), (100., 0.), (100., 100.)]);
+        assert_eq!(corner.simplified(1.0).points(), [(0., 0.), (100., 0.), (100., 100.)]);
+
+        let square = polyline(&[(0., 0.), (200., 0.), (200., 200.), (0., 200.), (0., 0.)]);
+        let corners = square.corners();
+        assert_eq!(corners.len(), 5);
+        for (corner, expected) in corners.iter().zip([0, 20, 40, 60, 80]) {
+            assert!(corner.abs_diff(expected) <= 1, "{:?}", corners);
+        }
+        let segments = square.segments();
+        assert_eq!(segments.len(), 4);
+        assert!(segments.iter().all(|s| s.straight));
+        assert!((segments[1].angle - core::f32::consts::FRAC_PI_2).abs() < 0.05);
+
+        let circle = circle();
+        assert_eq!(circle.corners(), vec![0, 80]);
+        assert!(!circle.segments()[0].straight);
+    }
+
+    #[test]
+    fn corner_counts_filter_matches() {
+        let templates = [Template::new("circle".to_owned(), &circle()).unwrap()];
+        let square = polyline(&[(0., 0.), (200., 0.), (200., 200.), (0., 200.), (0., 0.)]);
+        assert!(find_matching_template(&templates, &square, 45.0, 2.0).is_ok());
+        let corner_templates = CornerTemplates::new(&templates);
+        assert!(matches!(
+            find_matching_template_with_corners(&corner_templates, &square, 45.0, 2.0, 1),
+            Err(Error::NoMatch),
+        ));
+        let circle = circle();
+        let result = find_matching_template_with_corners(&corner_templates, &circle, 45.0, 2.0, 1);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn corner_matching_allows_for_invariance() {
+        let square = polyline(&[(0., 0.), (200., 0.), (200., 200.), (0., 200.), (0., 0.)]);
+        let mut templates = [Template::new("square".to_owned(), &square).unwrap()];
+        // Started partway along the right edge.
+        let drawn = [(200., 100.), (200., 200.), (0., 200.), (0., 0.), (200., 0.), (200., 100.)];
+        let drawn = polyline(&drawn);
+
+        let corner_templates = CornerTemplates::new(&templates);
+        let (_, score) =
+            find_matching_template_with_corners(&corner_templates, &drawn, 45.0, 2.0, 1).unwrap();
+        assert!(score < 0.9, "{}", score);
+
+        templates[0].invariance = Invariance { start_point: true, ..Invariance::default() };
+        let corner_templates = CornerTemplates::new(&templates);
+        let (_, score) =
+            find_matching_template_with_corners(&corner_templates, &drawn, 45.0, 2.0, 1).unwrap();
+        assert!(score > 0.95, "{}", score);
+    }
+}
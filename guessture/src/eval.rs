@@ -0,0 +1,589 @@
+//! Tools for measuring how well a set of templates recognizes labeled gesture samples.
+//!
+//! A [Dataset] of labeled [Path2D] samples can be evaluated against an existing set of
+//! templates with [evaluate], or split into folds with [cross_validate] to estimate how
+//! well templates built from the dataset generalize, either to new samples from the same
+//! users ([Mode::UserDependent]) or to users that were not seen during training
+//! ([Mode::UserIndependent]).
+
+use crate::augment::Augmenter;
+use crate::{find_matching_template, Path2D, Template};
+use alloc::borrow::ToOwned;
+use alloc::collections::BTreeSet;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+/// A single recorded gesture with its expected label.
+#[derive(Debug, Clone)]
+pub struct Sample {
+    /// The name of the gesture this sample represents.
+    pub label: String,
+    /// An identifier for the person that recorded this sample.
+    pub user: String,
+    /// The raw, unnormalized path of the sample.
+    pub path: Path2D,
+    /// For variants generated by [Dataset::augmented], the index of the sample they were
+    /// generated from. Cross-validation keeps variants in the same fold as their source.
+    pub variant_of: Option<usize>,
+}
+
+/// A collection of labeled gesture samples.
+#[derive(Debug, Clone, Default)]
+pub struct Dataset {
+    /// Every sample, in the order they were added.
+    pub samples: Vec<Sample>,
+}
+
+impl Dataset {
+    /// Add a new sample to this dataset.
+    pub fn push(&mut self, label: impl Into<String>, user: impl Into<String>, path: Path2D) {
+        self.samples.push(Sample {
+            label: label.into(),
+            user: user.into(),
+            path,
+            variant_of: None,
+        });
+    }
+
+    /// Returns a copy of this dataset where every sample is followed by `count` random
+    /// variants generated by `augmenter`, sharing the sample's label and user.
+    pub fn augmented(&self, augmenter: &mut Augmenter, count: usize) -> Dataset {
+        let mut augmented = Dataset::default();
+        for sample in &self.samples {
+            let source = augmented.samples.len();
+            augmented.samples.push(Sample {
+                variant_of: None,
+                ..sample.clone()
+            });
+            for path in augmenter.variants(&sample.path, count) {
+                augmented.samples.push(Sample {
+                    label: sample.label.clone(),
+                    user: sample.user.clone(),
+                    path,
+                    variant_of: Some(source),
+                });
+            }
+        }
+        augmented
+    }
+
+    /// Returns the sorted list of distinct labels in this dataset.
+    pub fn labels(&self) -> Vec<String> {
+        let labels: BTreeSet<_> = self.samples.iter().map(|s| s.label.clone()).collect();
+        labels.into_iter().collect()
+    }
+
+    /// Returns the sorted list of distinct users in this dataset.
+    pub fn users(&self) -> Vec<String> {
+        let users: BTreeSet<_> = self.samples.iter().map(|s| s.user.clone()).collect();
+        users.into_iter().collect()
+    }
+}
+
+/// How samples are split into training and testing folds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Each user's samples are split into folds independently; templates are built from
+    /// the remaining folds of the same user and tested against that user's held-out fold.
+    UserDependent,
+    /// Users are split into folds; templates are built from all samples of the users in
+    /// the remaining folds and tested against every sample of the held-out users.
+    UserIndependent,
+}
+
+/// Configuration for the matching process used during evaluation.
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    /// The number of folds to use for cross-validation.
+    pub folds: usize,
+    /// The range of rotation in degrees passed to [find_matching_template].
+    pub angle_range: f32,
+    /// The rotation precision in degrees passed to [find_matching_template].
+    pub angle_precision: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            folds: 5,
+            angle_range: 45.0,
+            angle_precision: 2.0,
+        }
+    }
+}
+
+/// The outcome of recognizing a single test sample.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Recognition {
+    /// The expected label of the sample.
+    pub label: String,
+    /// The user that recorded the sample.
+    pub user: String,
+    /// The name of the best matching template, or `None` if matching failed.
+    pub predicted: Option<String>,
+    /// The score of the best match, or 0.0 if matching failed.
+    pub score: f32,
+}
+
+impl Recognition {
+    /// Returns true if the sample was recognized as its expected label.
+    pub fn is_correct(&self) -> bool {
+        self.predicted.as_deref() == Some(self.label.as_str())
+    }
+}
+
+/// Precision and recall for a single class.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ClassMetrics {
+    /// The name of the class.
+    pub label: String,
+    /// The fraction of samples predicted as this class that actually belong to it.
+    pub precision: f32,
+    /// The fraction of samples belonging to this class that were predicted as it.
+    pub recall: f32,
+    /// The number of test samples belonging to this class.
+    pub support: usize,
+}
+
+const HISTOGRAM_BUCKETS: usize = 10;
+
+/// Summary statistics for a set of match scores.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ScoreSummary {
+    /// The number of scores.
+    pub count: usize,
+    /// The lowest score, or 0.0 if there are no scores.
+    pub min: f32,
+    /// The highest score, or 0.0 if there are no scores.
+    pub max: f32,
+    /// The mean score, or 0.0 if there are no scores.
+    pub mean: f32,
+    /// The median score, or 0.0 if there are no scores.
+    pub median: f32,
+    /// Number of scores falling in each of ten equal buckets between 0.0 and 1.0.
+    pub histogram: [usize; HISTOGRAM_BUCKETS],
+}
+
+impl ScoreSummary {
+    fn new(mut scores: Vec<f32>) -> ScoreSummary {
+        if scores.is_empty() {
+            return ScoreSummary::default();
+        }
+        scores.sort_by(|a, b| a.total_cmp(b));
+        let mut histogram = [0; HISTOGRAM_BUCKETS];
+        for score in &scores {
+            let bucket = (score.clamp(0.0, 1.0) * HISTOGRAM_BUCKETS as f32) as usize;
+            histogram[bucket.min(HISTOGRAM_BUCKETS - 1)] += 1;
+        }
+        let mid = scores.len() / 2;
+        let median = if scores.len() % 2 == 1 {
+            scores[mid]
+        } else {
+            (scores[mid - 1] + scores[mid]) / 2.0
+        };
+        ScoreSummary {
+            count: scores.len(),
+            min: scores[0],
+            max: scores[scores.len() - 1],
+            mean: scores.iter().sum::<f32>() / scores.len() as f32,
+            median,
+            histogram,
+        }
+    }
+}
+
+/// The results of an evaluation run.
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    /// The sorted list of every label that appeared as an expected or predicted class.
+    pub classes: Vec<String>,
+    /// The outcome of every test sample.
+    pub results: Vec<Recognition>,
+}
+
+impl Report {
+    fn from_results(results: Vec<Recognition>) -> Report {
+        let classes: BTreeSet<_> = results
+            .iter()
+            .flat_map(|r| core::iter::once(&r.label).chain(r.predicted.as_ref()))
+            .cloned()
+            .collect();
+        Report {
+            classes: classes.into_iter().collect(),
+            results,
+        }
+    }
+
+    fn class_index(&self, label: &str) -> usize {
+        self.classes.binary_search_by(|c| c.as_str().cmp(label)).unwrap()
+    }
+
+    /// The fraction of test samples that were recognized as their expected label.
+    /// Samples that could not be matched at all count as incorrect.
+    pub fn accuracy(&self) -> f32 {
+        if self.results.is_empty() {
+            return 0.0;
+        }
+        let correct = self.results.iter().filter(|r| r.is_correct()).count();
+        correct as f32 / self.results.len() as f32
+    }
+
+    /// Returns a matrix where `matrix[actual][predicted]` is the number of samples of the
+    /// class `classes[actual]` that were recognized as `classes[predicted]`. The final
+    /// column counts samples that could not be matched at all.
+    pub fn confusion_matrix(&self) -> Vec<Vec<usize>> {
+        let mut matrix = vec![vec![0; self.classes.len() + 1]; self.classes.len()];
+        for result in &self.results {
+            let actual = self.class_index(&result.label);
+            let predicted = match result.predicted {
+                Some(ref predicted) => self.class_index(predicted),
+                None => self.classes.len(),
+            };
+            matrix[actual][predicted] += 1;
+        }
+        matrix
+    }
+
+    /// Returns precision and recall for every class.
+    pub fn class_metrics(&self) -> Vec<ClassMetrics> {
+        let matrix = self.confusion_matrix();
+        self.classes
+            .iter()
+            .enumerate()
+            .map(|(i, label)| {
+                let true_positives = matrix[i][i];
+                let support: usize = matrix[i].iter().sum();
+                let predicted: usize = matrix.iter().map(|row| row[i]).sum();
+                let ratio = |n: usize, d: usize| if d == 0 { 0.0 } else { n as f32 / d as f32 };
+                ClassMetrics {
+                    label: label.clone(),
+                    precision: ratio(true_positives, predicted),
+                    recall: ratio(true_positives, support),
+                    support,
+                }
+            })
+            .collect()
+    }
+
+    /// Returns summaries of the scores of correct and incorrect matches, respectively.
+    /// Samples that could not be matched at all are not included.
+    pub fn score_distributions(&self) -> (ScoreSummary, ScoreSummary) {
+        let (correct, incorrect): (Vec<_>, Vec<_>) = self.results
+            .iter()
+            .filter(|r| r.predicted.is_some())
+            .partition(|r| r.is_correct());
+        (
+            ScoreSummary::new(correct.iter().map(|r| r.score).collect()),
+            ScoreSummary::new(incorrect.iter().map(|r| r.score).collect()),
+        )
+    }
+
+    /// Serialize the per-sample results as CSV with a header row.
+    pub fn results_csv(&self) -> String {
+        let mut csv = "label,user,predicted,score,correct\n".to_owned();
+        for result in &self.results {
+            let _ = writeln!(
+                csv,
+                "{},{},{},{},{}",
+                csv_field(&result.label),
+                csv_field(&result.user),
+                csv_field(result.predicted.as_deref().unwrap_or("")),
+                result.score,
+                result.is_correct(),
+            );
+        }
+        csv
+    }
+
+    /// Serialize the per-class metrics as CSV with a header row.
+    pub fn class_metrics_csv(&self) -> String {
+        let mut csv = "label,precision,recall,support\n".to_owned();
+        for metrics in self.class_metrics() {
+            let _ = writeln!(
+                csv,
+                "{},{},{},{}",
+                csv_field(&metrics.label),
+                metrics.precision,
+                metrics.recall,
+                metrics.support,
+            );
+        }
+        csv
+    }
+
+    /// Serialize the confusion matrix as CSV. Rows are actual classes and columns are
+    /// predicted classes, followed by a column for samples that could not be matched.
+    pub fn confusion_matrix_csv(&self) -> String {
+        let mut csv = "actual".to_owned();
+        for class in &self.classes {
+            csv.push(',');
+            csv.push_str(&csv_field(class));
+        }
+        csv.push_str(",(none)\n");
+        for (class, row) in self.classes.iter().zip(self.confusion_matrix()) {
+            csv.push_str(&csv_field(class));
+            for count in row {
+                let _ = write!(csv, ",{}", count);
+            }
+            csv.push('\n');
+        }
+        csv
+    }
+
+    /// Serialize the complete report, including all derived metrics, as JSON.
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        #[derive(serde::Serialize)]
+        struct JsonReport<'a> {
+            accuracy: f32,
+            classes: &'a [String],
+            confusion_matrix: Vec<Vec<usize>>,
+            class_metrics: Vec<ClassMetrics>,
+            correct_scores: ScoreSummary,
+            incorrect_scores: ScoreSummary,
+            results: &'a [Recognition],
+        }
+        let (correct_scores, incorrect_scores) = self.score_distributions();
+        serde_json::to_string_pretty(&JsonReport {
+            accuracy: self.accuracy(),
+            classes: &self.classes,
+            confusion_matrix: self.confusion_matrix(),
+            class_metrics: self.class_metrics(),
+            correct_scores,
+            incorrect_scores,
+            results: &self.results,
+        })
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_owned()
+    }
+}
+
+/// Recognize every sample against the provided templates.
+pub fn evaluate<'a>(
+    templates: &[Template],
+    samples: impl IntoIterator<Item = &'a Sample>,
+    config: &Config,
+) -> Report {
+    let samples: Vec<_> = samples.into_iter().collect();
+    Report::from_results(recognize_all(templates, &samples, config))
+}
+
+/// Recognize every sample, in order. With the `rayon` feature, samples are recognized in
+/// parallel.
+fn recognize_all(templates: &[Template], samples: &[&Sample], config: &Config) -> Vec<Recognition> {
+    #[cfg(feature = "rayon")]
+    {
+        use rayon::prelude::*;
+        samples.par_iter().map(|sample| recognize(templates, sample, config)).collect()
+    }
+    #[cfg(not(feature = "rayon"))]
+    samples.iter().map(|sample| recognize(templates, sample, config)).collect()
+}
+
+fn recognize(templates: &[Template], sample: &Sample, config: &Config) -> Recognition {
+    let matched = find_matching_template(
+        templates,
+        &sample.path,
+        config.angle_range,
+        config.angle_precision,
+    );
+    let (predicted, score) = match matched {
+        Ok((template, score)) => (Some(template.name.clone()), score),
+        Err(_) => (None, 0.0),
+    };
+    Recognition {
+        label: sample.label.clone(),
+        user: sample.user.clone(),
+        predicted,
+        score,
+    }
+}
+
+fn build_templates<'a>(samples: impl IntoIterator<Item = &'a Sample>) -> Vec<Template> {
+    samples
+        .into_iter()
+        .filter_map(|sample| Template::new(sample.label.clone(), &sample.path).ok())
+        .collect()
+}
+
+/// Split the dataset into `config.folds` folds according to `mode`, and evaluate each
+/// fold against templates built from the remaining folds. The results of every fold
+/// are combined into a single report.
+///
+/// Fold assignment is deterministic: samples are assigned round-robin in dataset order
+/// within each (user, label) group for [Mode::UserDependent], and users are assigned
+/// round-robin in sorted order for [Mode::UserIndependent]. Augmented variants are
+/// always assigned to the fold of the sample they were generated from, so that no test
+/// sample has a copy of itself among the training samples.
+pub fn cross_validate(dataset: &Dataset, mode: Mode, config: &Config) -> Report {
+    let folds = config.folds.max(2);
+    let fold_of = assign_folds(dataset, mode, folds);
+
+    let mut results = vec![];
+    match mode {
+        Mode::UserDependent => {
+            for user in dataset.users() {
+                for fold in 0..folds {
+                    let (train, test): (Vec<_>, Vec<_>) = dataset.samples
+                        .iter()
+                        .zip(&fold_of)
+                        .filter(|(sample, _)| sample.user == user)
+                        .partition(|(_, &f)| f != fold);
+                    let templates = build_templates(train.into_iter().map(|(s, _)| s));
+                    let test: Vec<_> = test.into_iter().map(|(s, _)| s).collect();
+                    results.extend(recognize_all(&templates, &test, config));
+                }
+            }
+        }
+        Mode::UserIndependent => {
+            for fold in 0..folds {
+                let (train, test): (Vec<_>, Vec<_>) = dataset.samples
+                    .iter()
+                    .zip(&fold_of)
+                    .partition(|(_, &f)| f != fold);
+                let templates = build_templates(train.into_iter().map(|(s, _)| s));
+                let test: Vec<_> = test.into_iter().map(|(s, _)| s).collect();
+                results.extend(recognize_all(&templates, &test, config));
+            }
+        }
+    }
+    Report::from_results(results)
+}
+
+fn assign_folds(dataset: &Dataset, mode: Mode, folds: usize) -> Vec<usize> {
+    match mode {
+        Mode::UserDependent => {
+            let mut seen: Vec<((&str, &str), usize)> = vec![];
+            let mut fold_of = Vec::with_capacity(dataset.samples.len());
+            for sample in &dataset.samples {
+                if let Some(&fold) = sample.variant_of.and_then(|source| fold_of.get(source)) {
+                    fold_of.push(fold);
+                    continue;
+                }
+                let key = (sample.user.as_str(), sample.label.as_str());
+                let fold = match seen.iter_mut().find(|(k, _)| *k == key) {
+                    Some((_, count)) => {
+                        *count += 1;
+                        *count % folds
+                    }
+                    None => {
+                        seen.push((key, 0));
+                        0
+                    }
+                };
+                fold_of.push(fold);
+            }
+            fold_of
+        }
+        Mode::UserIndependent => {
+            let users = dataset.users();
+            dataset.samples
+                .iter()
+                .map(|sample| users.binary_search(&sample.user).unwrap() % folds)
+                .collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(dx: f32, dy: f32, wobble: f32) -> Path2D {
+        let mut path = Path2D::default();
+        for i in 0..=20 {
+            let t = i as f32 / 20.0;
+            path.push(t * dx + wobble * (t * 7.0).sin(), t * dy + wobble * (t * 5.0).cos());
+        }
+        path
+    }
+
+    fn circle(radius: f32, wobble: f32) -> Path2D {
+        let mut path = Path2D::default();
+        for i in 0..=40 {
+            let t = i as f32 / 40.0 * core::f32::consts::TAU;
+            let r = radius + wobble * (t * 3.0).sin();
+            path.push(r * t.cos(), r * t.sin());
+        }
+        path
+    }
+
+    fn dataset() -> Dataset {
+        let mut dataset = Dataset::default();
+        for user in ["a", "b", "c"] {
+            for i in 0..4 {
+                let wobble = i as f32 * 2.0;
+                dataset.push("circle", user, circle(150.0, wobble));
+                dataset.push("zigzag", user, {
+                    let mut path = line(200.0, 0.0, wobble);
+                    for (x, y) in line(-200.0, 200.0, wobble).points() {
+                        path.push(x + 200.0, y);
+                    }
+                    path
+                });
+            }
+        }
+        dataset
+    }
+
+    #[test]
+    fn cross_validation_recognizes_distinct_classes() {
+        let dataset = dataset();
+        for mode in [Mode::UserDependent, Mode::UserIndependent] {
+            let report = cross_validate(&dataset, mode, &Config { folds: 2, ..Config::default() });
+            assert_eq!(report.results.len(), dataset.samples.len());
+            assert_eq!(report.accuracy(), 1.0);
+            assert_eq!(report.confusion_matrix(), vec![vec![12, 0, 0], vec![0, 12, 0]]);
+        }
+    }
+
+    #[test]
+    fn variants_share_their_source_fold() {
+        let mut augmenter = Augmenter::new(Default::default(), 7);
+        let dataset = dataset().augmented(&mut augmenter, 3);
+        assert_eq!(dataset.samples.len(), 24 * 4);
+        let fold_of = assign_folds(&dataset, Mode::UserDependent, 2);
+        for (i, sample) in dataset.samples.iter().enumerate() {
+            match sample.variant_of {
+                Some(source) => assert_eq!(fold_of[i], fold_of[source]),
+                None => assert_eq!(fold_of[i], i / 8 % 2),
+            }
+        }
+    }
+
+    #[test]
+    fn metrics_and_csv() {
+        let result = |label: &str, predicted: Option<&str>, score| Recognition {
+            label: label.to_owned(),
+            user: "u".to_owned(),
+            predicted: predicted.map(str::to_owned),
+            score,
+        };
+        let report = Report::from_results(vec![
+            result("a", Some("a"), 0.9),
+            result("a", Some("b"), 0.6),
+            result("b", Some("b"), 0.8),
+            result("b", None, 0.0),
+        ]);
+        assert_eq!(report.accuracy(), 0.5);
+        let metrics = report.class_metrics();
+        assert_eq!((metrics[0].precision, metrics[0].recall), (1.0, 0.5));
+        assert_eq!((metrics[1].precision, metrics[1].recall), (0.5, 0.5));
+        let (correct, incorrect) = report.score_distributions();
+        assert_eq!((correct.count, incorrect.count), (2, 1));
+        assert_eq!(correct.histogram[8] + correct.histogram[9], 2);
+        assert_eq!(report.confusion_matrix_csv(), "actual,a,b,(none)\na,1,1,0\nb,0,1,1\n");
+    }
+}
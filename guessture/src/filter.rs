@@ -0,0 +1,348 @@
+//! Filters for cleaning up raw input before it is normalized into a template or matched.
+//!
+//! Filters process one point at a time so that they can run live while a gesture is
+//! being recorded. A filter may transform a point, or discard it entirely. Filters can
+//! be combined with a [FilterChain], and applied to a complete path with
+//! [Path2D::filtered].
+
+use crate::{Path2D, PathCoord};
+use num_traits::Float;
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::f32::consts::PI;
+
+/// A filter applied to a stream of timestamped points.
+pub trait PointFilter {
+    /// Filter the next point, observed at `time` seconds. Returns the filtered point,
+    /// or `None` if the point should be discarded.
+    fn filter(&mut self, x: PathCoord, y: PathCoord, time: f32) -> Option<(PathCoord, PathCoord)>;
+
+    /// Forget all previous points, in preparation for a new stream.
+    fn reset(&mut self);
+}
+
+impl Path2D {
+    /// Returns a copy of this path with every point passed through `filter`, which is
+    /// reset first. Points are assumed to have been sampled at `rate` points per second.
+    pub fn filtered(&self, filter: &mut dyn PointFilter, rate: f32) -> Path2D {
+        filter.reset();
+        let mut filtered = Path2D::default();
+        for (i, point) in self.points.iter().enumerate() {
+            let time = i as f32 / rate;
+            if let Some((x, y)) = filter.filter(point.x, point.y, time) {
+                if filtered.is_new_point(x, y) {
+                    filtered.push(x, y);
+                }
+            }
+        }
+        filtered
+    }
+}
+
+/// Applies a sequence of filters in order. A point discarded by one filter is not seen
+/// by the filters that follow it.
+#[derive(Default)]
+pub struct FilterChain {
+    filters: Vec<Box<dyn PointFilter + Send + Sync>>,
+}
+
+impl FilterChain {
+    /// Returns this chain with `filter` appended.
+    pub fn with(mut self, filter: impl PointFilter + Send + Sync + 'static) -> FilterChain {
+        self.filters.push(Box::new(filter));
+        self
+    }
+}
+
+impl PointFilter for FilterChain {
+    fn filter(&mut self, x: PathCoord, y: PathCoord, time: f32) -> Option<(PathCoord, PathCoord)> {
+        self.filters
+            .iter_mut()
+            .try_fold((x, y), |(x, y), filter| filter.filter(x, y, time))
+    }
+
+    fn reset(&mut self) {
+        for filter in &mut self.filters {
+            filter.reset();
+        }
+    }
+}
+
+/// Discards points closer than `min_distance` to the last point that was kept.
+pub struct Decimation {
+    /// The smallest distance in pixels between kept points.
+    pub min_distance: PathCoord,
+    last: Option<(PathCoord, PathCoord)>,
+}
+
+impl Decimation {
+    /// Create a filter that keeps points at least `min_distance` pixels apart.
+    pub fn new(min_distance: PathCoord) -> Decimation {
+        Decimation {
+            min_distance,
+            last: None,
+        }
+    }
+}
+
+impl PointFilter for Decimation {
+    fn filter(&mut self, x: PathCoord, y: PathCoord, _time: f32) -> Option<(PathCoord, PathCoord)> {
+        if let Some((last_x, last_y)) = self.last {
+            if Float::hypot(x - last_x, y - last_y) < self.min_distance {
+                return None;
+            }
+        }
+        self.last = Some((x, y));
+        Some((x, y))
+    }
+
+    fn reset(&mut self) {
+        self.last = None;
+    }
+}
+
+/// Replaces each point with the average of the most recent `window` points.
+pub struct MovingAverage {
+    /// The number of points averaged, including the newest one.
+    pub window: usize,
+    recent: VecDeque<(PathCoord, PathCoord)>,
+}
+
+impl MovingAverage {
+    /// Create a filter that averages the last `window` points, which is at least 1.
+    pub fn new(window: usize) -> MovingAverage {
+        MovingAverage {
+            window: window.max(1),
+            recent: VecDeque::new(),
+        }
+    }
+}
+
+impl PointFilter for MovingAverage {
+    fn filter(&mut self, x: PathCoord, y: PathCoord, _time: f32) -> Option<(PathCoord, PathCoord)> {
+        self.recent.push_back((x, y));
+        while self.recent.len() > self.window {
+            self.recent.pop_front();
+        }
+        let count = self.recent.len() as PathCoord;
+        let (sum_x, sum_y) = self.recent
+            .iter()
+            .fold((0.0, 0.0), |(sx, sy), &(x, y)| (sx + x, sy + y));
+        Some((sum_x / count, sum_y / count))
+    }
+
+    fn reset(&mut self) {
+        self.recent.clear();
+    }
+}
+
+/// The number of steps that must be observed before spikes can be detected.
+const SPIKE_WARMUP_STEPS: usize = 3;
+
+/// Discards isolated points that jump much further from the previous point than the
+/// recent typical step length.
+pub struct SpikeRejection {
+    /// How many times longer than the typical step a jump must be to be rejected.
+    pub max_ratio: f32,
+    /// The most consecutive points that may be rejected. Once exceeded, the jump is
+    /// treated as genuine movement and accepted.
+    pub max_consecutive: usize,
+    last: Option<(PathCoord, PathCoord)>,
+    typical_step: f32,
+    steps: usize,
+    rejected: usize,
+}
+
+impl SpikeRejection {
+    /// Create a filter that rejects steps more than `max_ratio` times the typical step
+    /// length, up to 2 in a row.
+    pub fn new(max_ratio: f32) -> SpikeRejection {
+        SpikeRejection {
+            max_ratio,
+            max_consecutive: 2,
+            last: None,
+            typical_step: 0.0,
+            steps: 0,
+            rejected: 0,
+        }
+    }
+}
+
+impl PointFilter for SpikeRejection {
+    fn filter(&mut self, x: PathCoord, y: PathCoord, _time: f32) -> Option<(PathCoord, PathCoord)> {
+        let Some((last_x, last_y)) = self.last else {
+            self.last = Some((x, y));
+            return Some((x, y));
+        };
+        let step = Float::hypot(x - last_x, y - last_y);
+        let is_spike = self.steps >= SPIKE_WARMUP_STEPS &&
+            step > self.max_ratio * self.typical_step &&
+            self.rejected < self.max_consecutive;
+        if is_spike {
+            self.rejected += 1;
+            return None;
+        }
+
+        self.rejected = 0;
+        self.typical_step = if self.steps == 0 {
+            step
+        } else {
+            0.8 * self.typical_step + 0.2 * step
+        };
+        self.steps += 1;
+        self.last = Some((x, y));
+        Some((x, y))
+    }
+
+    fn reset(&mut self) {
+        self.last = None;
+        self.typical_step = 0.0;
+        self.steps = 0;
+        self.rejected = 0;
+    }
+}
+
+/// The interval assumed between points that arrive with identical timestamps.
+const DEFAULT_INTERVAL: f32 = 1.0 / 60.0;
+
+/// The One Euro filter (Casiez et al., CHI 2012): a low-pass filter whose cutoff
+/// frequency rises with speed, removing jitter when the pointer moves slowly while
+/// keeping lag low when it moves quickly.
+pub struct OneEuroFilter {
+    /// The cutoff frequency in Hz used when the pointer is still. Lower values remove
+    /// more jitter.
+    pub min_cutoff: f32,
+    /// How quickly the cutoff frequency rises with speed, in Hz per pixel per second.
+    /// Higher values reduce lag.
+    pub beta: f32,
+    /// The cutoff frequency in Hz used to smooth the speed estimate.
+    pub derivative_cutoff: f32,
+    state: Option<OneEuroState>,
+}
+
+struct OneEuroState {
+    x: (PathCoord, PathCoord),
+    dx: (PathCoord, PathCoord),
+    time: f32,
+    interval: f32,
+}
+
+impl OneEuroFilter {
+    /// Create a filter with a still cutoff of `min_cutoff` Hz and a speed coefficient of
+    /// `beta` Hz per pixel per second, smoothing the speed estimate at 1 Hz.
+    pub fn new(min_cutoff: f32, beta: f32) -> OneEuroFilter {
+        OneEuroFilter {
+            min_cutoff,
+            beta,
+            derivative_cutoff: 1.0,
+            state: None,
+        }
+    }
+}
+
+impl Default for OneEuroFilter {
+    fn default() -> Self {
+        OneEuroFilter::new(1.0, 0.007)
+    }
+}
+
+fn smoothing_factor(cutoff: f32, interval: f32) -> f32 {
+    let tau = 1.0 / (2.0 * PI * cutoff);
+    1.0 / (1.0 + tau / interval)
+}
+
+impl PointFilter for OneEuroFilter {
+    fn filter(&mut self, x: PathCoord, y: PathCoord, time: f32) -> Option<(PathCoord, PathCoord)> {
+        let Some(ref mut state) = self.state else {
+            self.state = Some(OneEuroState {
+                x: (x, y),
+                dx: (0.0, 0.0),
+                time,
+                interval: DEFAULT_INTERVAL,
+            });
+            return Some((x, y));
+        };
+
+        if time > state.time {
+            state.interval = time - state.time;
+            state.time = time;
+        }
+        let interval = state.interval;
+
+        let alpha_d = smoothing_factor(self.derivative_cutoff, interval);
+        let dx = (
+            alpha_d * (x - state.x.0) / interval + (1.0 - alpha_d) * state.dx.0,
+            alpha_d * (y - state.x.1) / interval + (1.0 - alpha_d) * state.dx.1,
+        );
+        let speed = Float::hypot(dx.0, dx.1);
+        let alpha = smoothing_factor(self.min_cutoff + self.beta * speed, interval);
+        let filtered = (
+            alpha * x + (1.0 - alpha) * state.x.0,
+            alpha * y + (1.0 - alpha) * state.x.1,
+        );
+
+        state.x = filtered;
+        state.dx = dx;
+        Some(filtered)
+    }
+
+    fn reset(&mut self) {
+        self.state = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(filter: &mut dyn PointFilter, points: &[(f32, f32)]) -> Vec<(f32, f32)> {
+        points
+            .iter()
+            .enumerate()
+            .filter_map(|(i, &(x, y))| filter.filter(x, y, i as f32 / 100.0))
+            .collect()
+    }
+
+    #[test]
+    fn simple_filters() {
+        let points = [(0., 0.), (1., 0.), (5., 0.), (5.5, 0.), (10., 0.)];
+        assert_eq!(
+            feed(&mut Decimation::new(2.0), &points),
+            vec![(0., 0.), (5., 0.), (10., 0.)],
+        );
+        assert_eq!(
+            feed(&mut MovingAverage::new(2), &points[..3]),
+            vec![(0., 0.), (0.5, 0.), (3., 0.)],
+        );
+
+        let mut line: Vec<_> = (0..10).map(|i| (i as f32, 0.)).collect();
+        line.insert(5, (4.5, 80.));
+        let filtered = feed(&mut SpikeRejection::new(5.0), &line);
+        assert_eq!(filtered.len(), 10);
+        assert!(filtered.iter().all(|&(_, y)| y == 0.0));
+
+        // A genuine jump is accepted once it persists.
+        let jump = [(0., 0.), (1., 0.), (2., 0.), (3., 0.), (100., 0.), (101., 0.), (102., 0.)];
+        assert_eq!(feed(&mut SpikeRejection::new(5.0), &jump).last(), Some(&(102., 0.)));
+    }
+
+    #[test]
+    fn one_euro_reduces_jitter() {
+        let noisy: Vec<_> = (0..200)
+            .map(|i| (100.0 + if i % 2 == 0 { 2.0 } else { -2.0 }, 50.0))
+            .collect();
+        let filtered = feed(&mut OneEuroFilter::default(), &noisy);
+        let tail = &filtered[100..];
+        assert!(tail.iter().all(|&(x, _)| (x - 100.0).abs() < 0.5));
+
+        let mut chain = FilterChain::default()
+            .with(OneEuroFilter::default())
+            .with(Decimation::new(1.0));
+        let mut path = Path2D::default();
+        for &(x, y) in &noisy {
+            path.push(x, y);
+        }
+        assert!(path.filtered(&mut chain, 100.0).points().len() < 5);
+    }
+}
@@ -0,0 +1,336 @@
+//! The JSON `.gestures` file format used to store sets of normalized templates.
+//!
+//! Files record the schema version and the parameters their templates were normalized
+//! with. Files written before the schema was versioned have no `version` field and are
+//! read as version 0, which used the same parameters as version 1. Templates normalized
+//! with different parameters than the recognizer's are renormalized when they are loaded.
+//! Files in the [binary] format do not record these parameters, so their templates must
+//! already be normalized with the recognizer's.
+
+use crate::binary::{self, Encoding};
+use crate::{Invariance, Path2D, PathCoord, Template, TemplateError, NUM_POINTS, SQUARE_SIZE};
+use std::fmt;
+
+/// The schema version written by [GestureFile::to_json]. Files with a newer version are
+/// rejected.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// The serialized form of a single normalized template.
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct TemplateData {
+    /// The name of the gesture the template represents.
+    pub name: String,
+    /// The normalized points of the template.
+    pub path: Vec<(PathCoord, PathCoord)>,
+    /// The variations of the gesture that match the template. Omitted when there are
+    /// none.
+    #[serde(default, skip_serializing_if = "Invariance::is_none")]
+    pub invariance: Invariance,
+}
+
+impl TemplateData {
+    /// Copy the name, points and invariance of a template.
+    pub fn from_template(template: &Template) -> TemplateData {
+        TemplateData {
+            name: template.name.clone(),
+            path: template.path.points(),
+            invariance: template.invariance,
+        }
+    }
+
+    /// Create a template from the previously-normalized points of this data.
+    pub fn to_template(&self) -> Result<Template, TemplateError> {
+        let mut path = Path2D::default();
+        for &(x, y) in &self.path {
+            path.push(x, y);
+        }
+        Ok(Template::new_from_template(self.name.clone(), path)?.with_invariance(self.invariance))
+    }
+}
+
+/// The contents of a `.gestures` file.
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct GestureFile {
+    /// The schema version of the file; 0 for files that predate versioning.
+    #[serde(default)]
+    pub version: u32,
+    /// The number of points in every template.
+    #[serde(default = "default_num_points")]
+    pub num_points: usize,
+    /// The size of the square that templates were scaled to.
+    #[serde(default = "default_square_size")]
+    pub square_size: f64,
+    /// The templates in the file, in the order they are matched.
+    pub templates: Vec<TemplateData>,
+}
+
+fn default_num_points() -> usize {
+    NUM_POINTS
+}
+
+fn default_square_size() -> f64 {
+    SQUARE_SIZE
+}
+
+impl Default for GestureFile {
+    fn default() -> GestureFile {
+        GestureFile::from_templates(&[])
+    }
+}
+
+/// The ways in which a `.gestures` file can fail to be read.
+#[derive(Debug)]
+pub enum Error {
+    /// The file is not valid JSON, or does not match the schema.
+    Json(serde_json::Error),
+    /// The file was written with a newer version of the schema.
+    UnsupportedVersion(u32),
+    /// The normalization parameters are out of range.
+    InvalidParameters,
+    /// A template does not have the number of points given by the file.
+    WrongPointCount {
+        /// The index of the template in the file.
+        template: usize,
+        /// The number of points given by the file.
+        expected: usize,
+        /// The number of points in the template.
+        found: usize,
+    },
+    /// A template contains a coordinate that is infinite or NaN.
+    NonFinitePoint {
+        /// The index of the template in the file.
+        template: usize,
+    },
+    /// A template has no points, so it cannot be turned into a [Template].
+    InvalidTemplate {
+        /// The index of the template in the file.
+        template: usize,
+    },
+    /// The file is not valid in the [binary] format.
+    Binary(binary::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Json(err) => write!(f, "{}", err),
+            Error::UnsupportedVersion(version) => {
+                write!(f, "unsupported schema version {} (newest is {})", version, FORMAT_VERSION)
+            }
+            Error::InvalidParameters => write!(f, "invalid normalization parameters"),
+            Error::WrongPointCount { template, expected, found } => {
+                write!(f, "template {} has {} points instead of {}", template, found, expected)
+            }
+            Error::NonFinitePoint { template } => {
+                write!(f, "template {} has a non-finite coordinate", template)
+            }
+            Error::InvalidTemplate { template } => {
+                write!(f, "template {} cannot be normalized", template)
+            }
+            Error::Binary(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Error {
+        Error::Json(err)
+    }
+}
+
+impl From<binary::Error> for Error {
+    fn from(err: binary::Error) -> Error {
+        Error::Binary(err)
+    }
+}
+
+impl GestureFile {
+    /// Create a file holding the provided templates, recording the current schema version
+    /// and normalization parameters.
+    pub fn from_templates(templates: &[Template]) -> GestureFile {
+        GestureFile {
+            version: FORMAT_VERSION,
+            num_points: NUM_POINTS,
+            square_size: SQUARE_SIZE,
+            templates: templates.iter().map(TemplateData::from_template).collect(),
+        }
+    }
+
+    /// Check that every template has the file's number of points, and that every
+    /// coordinate is finite.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.version > FORMAT_VERSION {
+            return Err(Error::UnsupportedVersion(self.version));
+        }
+        if self.num_points < 2 || !(self.square_size.is_finite() && self.square_size > 0.0) {
+            return Err(Error::InvalidParameters);
+        }
+        for (index, template) in self.templates.iter().enumerate() {
+            if template.path.len() != self.num_points {
+                return Err(Error::WrongPointCount {
+                    template: index,
+                    expected: self.num_points,
+                    found: template.path.len(),
+                });
+            }
+            if template.path.iter().any(|(x, y)| !x.is_finite() || !y.is_finite()) {
+                return Err(Error::NonFinitePoint { template: index });
+            }
+        }
+        Ok(())
+    }
+
+    /// Upgrade this file to the current schema version. Templates that were normalized with
+    /// different parameters than the recognizer uses are renormalized, and the result is
+    /// validated again.
+    pub fn migrate(self) -> Result<GestureFile, Error> {
+        if self.num_points == NUM_POINTS && self.square_size == SQUARE_SIZE {
+            return Ok(GestureFile { version: FORMAT_VERSION, ..self });
+        }
+        let templates = self
+            .templates
+            .iter()
+            .enumerate()
+            .map(|(index, data)| {
+                let mut path = Path2D::default();
+                for &(x, y) in &data.path {
+                    path.push(x, y);
+                }
+                let template = Template::new(data.name.clone(), &path)
+                    .map_err(|_| Error::InvalidTemplate { template: index })?;
+                Ok(template.with_invariance(data.invariance))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let file = GestureFile::from_templates(&templates);
+        file.validate()?;
+        Ok(file)
+    }
+
+    /// Returns the templates stored in this file.
+    pub fn to_templates(&self) -> Result<Vec<Template>, Error> {
+        self.templates
+            .iter()
+            .enumerate()
+            .map(|(index, data)| {
+                data.to_template().map_err(|_| Error::InvalidTemplate { template: index })
+            })
+            .collect()
+    }
+
+    /// Parse the contents of a `.gestures` file, validate it, and migrate it to the current
+    /// schema version.
+    pub fn from_json(json: &str) -> Result<GestureFile, Error> {
+        let file: GestureFile = serde_json::from_str(json)?;
+        file.validate()?;
+        file.migrate()
+    }
+
+    /// Returns the contents of this file as JSON, in the current schema version.
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+
+    /// Read and validate a file in the [binary] template format. Converting a file to the
+    /// binary format with [Encoding::F32] and back reproduces it exactly.
+    pub fn from_binary(bytes: &[u8]) -> Result<GestureFile, Error> {
+        let file = GestureFile::from_templates(&binary::decode(bytes)?);
+        file.validate()?;
+        Ok(file)
+    }
+
+    /// Returns the contents of this file in the [binary] template format.
+    pub fn to_binary(&self, encoding: Encoding) -> Result<Vec<u8>, Error> {
+        Ok(binary::encode(&self.to_templates()?, encoding))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points(count: usize) -> String {
+        let points: Vec<String> = (0..count).map(|i| format!("[{}.0,{}.5]", i, i % 7)).collect();
+        points.join(",")
+    }
+
+    #[test]
+    fn migrate_old_files() {
+        // Files written before versioning have no version or parameters.
+        let old = format!(r#"{{"templates":[{{"name":"a","path":[{}]}}]}}"#, points(64));
+        let file = GestureFile::from_json(&old).unwrap();
+        assert_eq!((file.version, file.num_points), (FORMAT_VERSION, NUM_POINTS));
+        assert_eq!(file.templates[0].path[63], (63.0, 0.5));
+        let saved = file.to_json().unwrap();
+        assert!(saved.starts_with(r#"{"version":1,"num_points":64,"square_size":250.0,"#));
+
+        // Templates with other parameters are renormalized.
+        let template = format!(r#"{{"name":"a","path":[{}]}}"#, points(32));
+        let other = format!(
+            r#"{{"version":1,"num_points":32,"square_size":2.0,"templates":[{}]}}"#,
+            template,
+        );
+        let file = GestureFile::from_json(&other).unwrap();
+        assert_eq!(file.num_points, NUM_POINTS);
+        assert_eq!(file.templates[0].path.len(), NUM_POINTS);
+    }
+
+    #[test]
+    fn reject_invalid_files() {
+        let short = format!(r#"{{"templates":[{{"name":"a","path":[{}]}}]}}"#, points(10));
+        assert!(matches!(
+            GestureFile::from_json(&short),
+            Err(Error::WrongPointCount { template: 0, expected: 64, found: 10 }),
+        ));
+        let newer = r#"{"version":2,"templates":[]}"#;
+        assert!(matches!(GestureFile::from_json(newer), Err(Error::UnsupportedVersion(2))));
+        assert!(matches!(GestureFile::from_json("{"), Err(Error::Json(_))));
+
+        let mut file = GestureFile::default();
+        file.templates.push(TemplateData {
+            name: "a".to_owned(),
+            path: vec![(PathCoord::NAN, 0.0); NUM_POINTS],
+            invariance: Invariance::default(),
+        });
+        assert!(matches!(file.validate(), Err(Error::NonFinitePoint { template: 0 })));
+
+        // Binary files are validated the same way.
+        let template = |name: &str, points: &[(f32, f32)]| {
+            let mut path = Path2D::default();
+            for &(x, y) in points {
+                path.push(x, y);
+            }
+            Template::new_from_template(name.to_owned(), path).unwrap()
+        };
+        let short: Vec<_> = (0..10).map(|i| (i as f32, 0.0)).collect();
+        let bytes = binary::encode(&[template("short", &short)], Encoding::F32);
+        assert!(matches!(
+            GestureFile::from_binary(&bytes),
+            Err(Error::WrongPointCount { template: 0, expected: 64, found: 10 }),
+        ));
+        let nan = vec![(PathCoord::NAN, 0.0); NUM_POINTS];
+        let bytes = binary::encode(&[template("nan", &nan)], Encoding::F32);
+        assert!(matches!(
+            GestureFile::from_binary(&bytes),
+            Err(Error::NonFinitePoint { template: 0 }),
+        ));
+        // A NaN scale in the i16 encoding, which follows the header, name, flags and count.
+        let line: Vec<_> = (0..NUM_POINTS).map(|i| (i as f32, 0.0)).collect();
+        let mut bytes = binary::encode(&[template("nan", &line)], Encoding::I16);
+        bytes[24..28].copy_from_slice(&PathCoord::NAN.to_le_bytes());
+        assert!(matches!(
+            GestureFile::from_binary(&bytes),
+            Err(Error::NonFinitePoint { template: 0 }),
+        ));
+        assert!(matches!(
+            GestureFile::from_binary(b"GSTR"),
+            Err(Error::Binary(binary::Error::Truncated)),
+        ));
+
+        // Renormalizing a straight line fails rather than producing non-finite points.
+        let line = r#"{"num_points":3,"templates":[{"name":"a","path":[[0,0],[1,1],[2,2]]}]}"#;
+        let result = GestureFile::from_json(line);
+        assert!(matches!(result, Err(Error::NonFinitePoint { template: 0 })), "{:?}", result);
+    }
+}
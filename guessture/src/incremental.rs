@@ -0,0 +1,181 @@
+//! Recognition of gestures that are still being drawn.
+//!
+//! Every template is split into prefixes covering increasing fractions of its length,
+//! and each prefix is normalized like a complete template. A partial path is compared
+//! against every prefix; the best matching prefix of each template indicates both how
+//! likely that gesture is and how much of it has been drawn so far.
+
+use crate::{find_matching_templates, Error, Path2D, Template};
+use num_traits::Float;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// The default number of prefixes each template is split into.
+const DEFAULT_STEPS: usize = 10;
+
+/// The smallest number of template points a prefix may contain.
+const MIN_PREFIX_POINTS: usize = 3;
+
+struct Prefix {
+    /// The index of the template this prefix was created from.
+    template: usize,
+    /// The fraction of the template covered by this prefix.
+    progress: f32,
+}
+
+/// A gesture that a partial path may turn out to be.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    /// The index of the template in the set the recognizer was created from.
+    pub template: usize,
+    /// The name of the template.
+    pub name: String,
+    /// How closely the partial path matches the best prefix of the template, between
+    /// 0.0 and 1.0.
+    pub score: f32,
+    /// The estimated fraction of the template that has been drawn, between 0.0 and 1.0.
+    pub progress: f32,
+}
+
+/// Matches partial paths against prefixes of a set of templates.
+pub struct IncrementalRecognizer {
+    /// The normalized prefixes of every template, matched together as one set.
+    normalized: Vec<Template>,
+    /// Where each of the normalized prefixes came from.
+    prefixes: Vec<Prefix>,
+    names: Vec<String>,
+    /// The range of rotation in degrees in which paths are compared against prefixes.
+    pub angle_range: f32,
+    /// The precision in degrees at which rotations are attempted.
+    pub angle_precision: f32,
+}
+
+impl IncrementalRecognizer {
+    /// Create a recognizer for the provided templates, splitting each of them into ten
+    /// prefixes and matching within a 90 degree range with 2 degree precision.
+    pub fn new(templates: &[Template]) -> IncrementalRecognizer {
+        IncrementalRecognizer::with_steps(templates, DEFAULT_STEPS)
+    }
+
+    /// Create a recognizer for the provided templates, splitting each of them into
+    /// `steps` prefixes of evenly increasing length.
+    pub fn with_steps(templates: &[Template], steps: usize) -> IncrementalRecognizer {
+        let steps = steps.max(1);
+        let mut normalized = vec![];
+        let mut prefixes = vec![];
+        for (index, template) in templates.iter().enumerate() {
+            let points = template.path.points();
+            for step in 1..=steps {
+                let progress = step as f32 / steps as f32;
+                let count = Float::round(progress * points.len() as f32) as usize;
+                if count < MIN_PREFIX_POINTS.min(points.len()) {
+                    continue;
+                }
+                let mut path = Path2D::default();
+                for &(x, y) in &points[..count] {
+                    path.push(x, y);
+                }
+                let Ok(prefix) = Template::new(template.name.clone(), &path) else {
+                    continue
+                };
+                // Nearly straight prefixes cannot be normalized to a square.
+                if !prefix.path.is_finite() {
+                    continue;
+                }
+                normalized.push(prefix);
+                prefixes.push(Prefix { template: index, progress });
+            }
+        }
+        IncrementalRecognizer {
+            normalized,
+            prefixes,
+            names: templates.iter().map(|t| t.name.clone()).collect(),
+            angle_range: 45.0,
+            angle_precision: 2.0,
+        }
+    }
+
+    /// Compare a partial path against every template, returning one candidate per
+    /// template ordered from most to least likely. Returns an error if the path is too
+    /// short or too straight to compare meaningfully.
+    pub fn recognize(&self, path: &Path2D) -> Result<Vec<Candidate>, Error> {
+        let matches = find_matching_templates(
+            &self.normalized,
+            path,
+            self.angle_range,
+            self.angle_precision,
+            self.normalized.len(),
+        );
+        let matches = match matches {
+            // Every prefix can be compared with a path that can be normalized.
+            Err(Error::NoMatch) if !self.normalized.is_empty() => return Err(Error::TooShort),
+            result => result?,
+        };
+
+        // Matches are best first, so the first one of each template is its best prefix.
+        let mut seen = vec![false; self.names.len()];
+        let candidates: Vec<_> = matches
+            .into_iter()
+            .filter_map(|(index, score)| {
+                let prefix = &self.prefixes[index];
+                if core::mem::replace(&mut seen[prefix.template], true) {
+                    return None;
+                }
+                Some(Candidate {
+                    template: prefix.template,
+                    name: self.names[prefix.template].clone(),
+                    score,
+                    progress: prefix.progress,
+                })
+            })
+            .collect();
+        Ok(candidates)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(points: &[(f32, f32)]) -> Path2D {
+        let mut path = Path2D::default();
+        for pair in points.windows(2) {
+            let [(x0, y0), (x1, y1)] = pair else { unreachable!() };
+            for i in 0..20 {
+                let t = i as f32 / 20.0;
+                path.push(x0 + (x1 - x0) * t, y0 + (y1 - y0) * t);
+            }
+        }
+        let &(x, y) = points.last().unwrap();
+        path.push(x, y);
+        path
+    }
+
+    #[test]
+    fn partial_paths_report_progress() {
+        let square = [(0., 0.), (200., 0.), (200., 200.), (0., 200.), (0., 0.)];
+        let zigzag = [(0., 0.), (200., 0.), (0., 200.), (200., 200.)];
+        let templates = [
+            Template::new("square".to_owned(), &path(&square)).unwrap(),
+            Template::new("zigzag".to_owned(), &path(&zigzag)).unwrap(),
+        ];
+        let recognizer = IncrementalRecognizer::new(&templates);
+
+        let short = path(&[(0., 0.), (50., 0.)]);
+        assert!(matches!(recognizer.recognize(&short), Err(Error::TooShort)));
+        let straight = path(&[(0., 0.), (150., 0.)]);
+        assert!(matches!(recognizer.recognize(&straight), Err(Error::TooShort)));
+
+        // Three of the four sides of the square.
+        let partial = path(&square[..4]);
+        let candidates = recognizer.recognize(&partial).unwrap();
+        assert_eq!(candidates.len(), 2);
+        assert_eq!(candidates[0].name, "square");
+        assert!((candidates[0].progress - 0.75).abs() <= 0.1);
+
+        let complete = recognizer.recognize(&path(&square)).unwrap();
+        assert_eq!(complete[0].name, "square");
+        assert_eq!(complete[0].progress, 1.0);
+    }
+}
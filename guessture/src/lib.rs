@@ -1,224 +1,581 @@
-use euclid::Angle;
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+use euclid::{Angle, Trig};
 use euclid::default::{Box2D, Point2D};
+// Without the `std` feature, float math comes from `libm` through `Float`. Modules call it
+// on concrete floats as `Float::sqrt(x)`: with `x.sqrt()`, the import would only be used
+// when the standard library's inherent float methods are missing.
+use num_traits::{Float, NumAssign};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::Debug;
+use core::iter::Sum;
+
+pub mod analysis;
+pub mod augment;
+pub mod binary;
+pub mod corners;
+pub mod eval;
+pub mod filter;
+#[cfg(feature = "serde")]
+pub mod format;
+pub mod incremental;
+pub mod primitives;
+pub mod segment;
+#[cfg(feature = "svg")]
+pub mod svg;
+#[cfg(feature = "xml")]
+pub mod xml;
 
 const NUM_POINTS: usize = 64;
-const SQUARE_SIZE: f32 = 250.0;
+const SQUARE_SIZE: f64 = 250.0;
+
+/// The largest gap between the ends of a closed stroke, relative to its length.
+const CLOSED_GAP: PathCoord = 0.15;
 
+/// The number of start points tried when matching closed strokes against templates that
+/// allow any start point.
+const START_POINTS: usize = 16;
+
+/// The default type of path coordinates.
 pub type PathCoord = f32;
 
+/// A floating point type that path coordinates can be stored in. Implemented for `f32`,
+/// which is used by default, and `f64`.
+pub trait Coord:
+    Float + NumAssign + Trig + Sum + Default + Debug + Send + Sync + 'static + sealed::Sealed
+{
+}
+
+impl Coord for f32 {}
+impl Coord for f64 {}
+
+mod sealed {
+    /// Conversions needed by the matcher that [num_traits::Float] does not provide.
+    pub trait Sealed: Sized {
+        /// Convert a constant to this type.
+        fn constant(value: f64) -> Self;
+        /// Convert a number of points to this type.
+        fn from_count(count: usize) -> Self;
+        /// Returns the bits of a value that is not negative. Larger values have larger
+        /// bits.
+        fn to_ordered_bits(self) -> u64;
+        /// The inverse of [Sealed::to_ordered_bits].
+        fn from_ordered_bits(bits: u64) -> Self;
+        /// Compare two values with the IEEE 754 total order.
+        fn total_cmp(&self, other: &Self) -> core::cmp::Ordering;
+    }
+
+    impl Sealed for f32 {
+        fn constant(value: f64) -> Self {
+            value as f32
+        }
+
+        fn from_count(count: usize) -> Self {
+            count as f32
+        }
+
+        fn to_ordered_bits(self) -> u64 {
+            self.to_bits() as u64
+        }
+
+        fn total_cmp(&self, other: &Self) -> core::cmp::Ordering {
+            f32::total_cmp(self, other)
+        }
+
+        fn from_ordered_bits(bits: u64) -> Self {
+            f32::from_bits(bits as u32)
+        }
+    }
+
+    impl Sealed for f64 {
+        fn constant(value: f64) -> Self {
+            value
+        }
+
+        fn from_count(count: usize) -> Self {
+            count as f64
+        }
+
+        fn to_ordered_bits(self) -> u64 {
+            self.to_bits()
+        }
+
+        fn total_cmp(&self, other: &Self) -> core::cmp::Ordering {
+            f64::total_cmp(self, other)
+        }
+
+        fn from_ordered_bits(bits: u64) -> Self {
+            f64::from_bits(bits)
+        }
+    }
+}
+
 /// A 2d path made up of (x, y) point values.
 #[derive(Default, Debug, Clone)]
-pub struct Path2D {
-    points: Vec<Point2D<PathCoord>>,
+pub struct Path2D<T = PathCoord> {
+    points: Vec<Point2D<T>>,
 }
 
-impl Path2D {
+impl<T: Coord> Path2D<T> {
     /// Returns the list of points that make up this path.
-    pub fn points(&self) -> Vec<(PathCoord, PathCoord)> {
+    pub fn points(&self) -> Vec<(T, T)> {
         self.points.iter().map(|p| (p.x, p.y)).collect()
     }
 
     /// Add a new point to this path.
-    pub fn push(&mut self, x: PathCoord, y: PathCoord) {
+    pub fn push(&mut self, x: T, y: T) {
         self.points.push(Point2D::new(x, y));
     }
 
     /// Returns true if the provided point is different than the last point in this path.
-    pub fn is_new_point(&self, x: PathCoord, y: PathCoord) -> bool {
+    pub fn is_new_point(&self, x: T, y: T) -> bool {
         let last = self.points.last();
         last.map_or(true, |last| *last != Point2D::new(x, y))
     }
 
-    fn length(&self) -> PathCoord {
-        let mut total: PathCoord = 0.0;
-        for points in self.points.windows(2) {
-            let [point_a, point_b] = points else { continue };
-            total += point_b.distance_to(*point_a);
-        }
-        assert!(total >= 0.0);
-        return total;
+    fn length(&self) -> T {
+        let total = path_length(&self.points);
+        assert!(total >= T::zero());
+        total
+    }
+
+    /// Returns true if every coordinate in this path is finite. Normalizing a path
+    /// with a zero-width or zero-height bounding box produces non-finite coordinates.
+    fn is_finite(&self) -> bool {
+        self.points.iter().all(|p| p.x.is_finite() && p.y.is_finite())
+    }
+
+    fn resample(&self, num_points: usize) -> Path2D<T> {
+        let mut resampled = Path2D::default();
+        resample_into(&self.points, num_points, &mut resampled.points);
+        resampled
+    }
+
+    fn centroid(&self) -> Point2D<T> {
+        centroid(&self.points)
+    }
+
+    fn rotate_by(&self, radians: T) -> Path2D<T> {
+        let mut rotated = self.clone();
+        rotate_by(&mut rotated.points, radians);
+        rotated
+    }
+
+    fn bounding_rect(&self) -> Box2D<T> {
+        bounding_rect(&self.points)
     }
 
-    fn indicative_angle(&self) -> f32 {
+    /// Returns a copy of this path reflected around its centroid.
+    pub fn mirrored(&self, mirror: Mirror) -> Path2D<T> {
+        if self.points.is_empty() {
+            return self.clone();
+        }
         let centroid = self.centroid();
-        return (centroid.y - self.points[0].y).atan2(centroid.x - self.points[0].x);
+        let points = self.points
+            .iter()
+            .map(|point| {
+                let (x, y) = mirror.apply(point.x - centroid.x, point.y - centroid.y);
+                Point2D::new(x + centroid.x, y + centroid.y)
+            })
+            .collect();
+        Path2D { points }
     }
+}
 
-    #[allow(non_snake_case)]
-    fn resample(&self, num_points: usize) -> Path2D {
-        let interval_length = self.length() / (num_points - 1) as PathCoord;
-        let mut D: PathCoord = 0.0;
+fn path_length<T: Coord>(points: &[Point2D<T>]) -> T {
+    let mut total = T::zero();
+    for points in points.windows(2) {
+        let [point_a, point_b] = points else { continue };
+        total += point_b.distance_to(*point_a);
+    }
+    total
+}
 
-        let mut old_points = self.points.clone();
+fn indicative_angle<T: Coord>(points: &[Point2D<T>]) -> T {
+    let centroid = centroid(points);
+    (centroid.y - points[0].y).atan2(centroid.x - points[0].x)
+}
 
-        let mut resampled = Path2D {
-            points: vec![self.points[0]],
-        };
+/// Replace the contents of `resampled` with `num_points` points spaced evenly along
+/// `points`.
+#[allow(non_snake_case)]
+fn resample_into<T: Coord>(
+    points: &[Point2D<T>],
+    num_points: usize,
+    resampled: &mut Vec<Point2D<T>>,
+) {
+    let interval_length = path_length(points) / T::from_count(num_points - 1);
+    let mut D = T::zero();
 
-        let mut i = 1;
-        while i < old_points.len() {
-            let d = old_points[i].distance_to(old_points[i - 1]);
-
-            if D + d > interval_length {
-                let qx = old_points[i - 1].x +
-                    ((interval_length - D) / d) *
-                    (old_points[i].x - old_points[i - 1].x);
-                let qy = old_points[i - 1].y +
-                    ((interval_length - D) / d) *
-                    (old_points[i].y - old_points[i - 1].y);
-
-                let point = Point2D::new(qx, qy);
-                resampled.points.push(point);
-                old_points.insert(i, point);
-                D = 0.0;
-            } else {
-                D += d;
-            }
+    resampled.clear();
+    resampled.push(points[0]);
+
+    // Each new point becomes the start of the remainder of the segment it was placed
+    // on, so `previous` is not always one of the original points.
+    let mut previous = points[0];
+    let mut i = 1;
+    while i < points.len() {
+        let d = points[i].distance_to(previous);
 
+        if D + d > interval_length {
+            let qx = previous.x +
+                ((interval_length - D) / d) *
+                (points[i].x - previous.x);
+            let qy = previous.y +
+                ((interval_length - D) / d) *
+                (points[i].y - previous.y);
+
+            let point = Point2D::new(qx, qy);
+            resampled.push(point);
+            previous = point;
+            D = T::zero();
+        } else {
+            D += d;
+            previous = points[i];
             i += 1;
         }
+    }
 
-        if resampled.points.len() == num_points - 1 {
-            resampled.points.push(old_points[old_points.len() - 1]);
-        }
-        return resampled;
+    if resampled.len() == num_points - 1 {
+        resampled.push(points[points.len() - 1]);
     }
+}
 
-    fn centroid(&self) -> Point2D<PathCoord> {
-        let mut qx: PathCoord = 0.0;
-        let mut qy: PathCoord = 0.0;
+fn centroid<T: Coord>(points: &[Point2D<T>]) -> Point2D<T> {
+    let mut qx = T::zero();
+    let mut qy = T::zero();
 
-        for point in &self.points {
-            qx += point.x;
-            qy += point.y;
-        }
+    for point in points {
+        qx += point.x;
+        qy += point.y;
+    }
 
-        qx /= self.points.len() as PathCoord;
-        qy /= self.points.len() as PathCoord;
+    qx /= T::from_count(points.len());
+    qy /= T::from_count(points.len());
 
-        return Point2D::new(qx, qy);
+    Point2D::new(qx, qy)
+}
+
+fn rotate_by<T: Coord>(points: &mut [Point2D<T>], radians: T) {
+    let centroid = centroid(points);
+    let (sin, cos) = radians.sin_cos();
+    for point in points {
+        *point = rotate_around(*point, centroid, sin, cos);
     }
+}
 
-    fn rotate_by(&self, radians: f32) -> Path2D {
-        let centroid = self.centroid();
-        let (sin, cos) = radians.sin_cos();
-        Path2D {
-            points: self.points
-                .iter()
-                .map(|point| {
-                    let adjusted = *point - centroid;
-                    let qx = adjusted.x * cos -
-                        adjusted.y * sin +
-                        centroid.x;
-                    let qy = adjusted.x * sin +
-                        adjusted.y * cos +
-                        centroid.y;
-                    Point2D::new(qx, qy)
-                })
-                .collect()
-        }
+fn rotate_around<T: Coord>(
+    point: Point2D<T>,
+    centroid: Point2D<T>,
+    sin: T,
+    cos: T,
+) -> Point2D<T> {
+    let adjusted = point - centroid;
+    let qx = adjusted.x * cos -
+        adjusted.y * sin +
+        centroid.x;
+    let qy = adjusted.x * sin +
+        adjusted.y * cos +
+        centroid.y;
+    Point2D::new(qx, qy)
+}
+
+#[allow(non_snake_case)]
+fn scale_by<T: Coord>(points: &mut [Point2D<T>], size: T) {
+    let B = bounding_rect(points);
+    for point in points {
+        point.x *= size / B.width();
+        point.y *= size / B.height();
     }
+}
 
-    #[allow(non_snake_case)]
-    fn scale_by(&self, size: f32) -> Path2D {
-        let B = self.bounding_rect();
-        let mut scaled = Path2D {
-            points: vec![],
-        };
-        for point in &self.points {
-            let qx = point.x * (size / B.width());
-            let qy = point.y * (size / B.height());
-            scaled.points.push(Point2D::new(qx, qy));
-        }
-        return scaled;
+fn bounding_rect<T: Coord>(points: &[Point2D<T>]) -> Box2D<T> {
+    let mut min_x = T::max_value();
+    let mut max_x = T::min_value();
+    let mut min_y = T::max_value();
+    let mut max_y = T::min_value();
+    for point in points {
+        min_x = min_x.min(point.x);
+        max_x = max_x.max(point.x);
+        min_y = min_y.min(point.y);
+        max_y = max_y.max(point.y);
     }
+    Box2D::new(
+        Point2D::new(min_x, min_y),
+        Point2D::new(max_x, max_y),
+    )
+}
 
-    fn bounding_rect(&self) -> Box2D<PathCoord> {
-        let mut min_x = f32::MAX;
-        let mut max_x = f32::MIN;
-        let mut min_y = f32::MAX;
-        let mut max_y = f32::MIN;
-        for point in &self.points {
-            min_x = min_x.min(point.x);
-            max_x = max_x.max(point.x);
-            min_y = min_y.min(point.y);
-            max_y = max_y.max(point.y);
-        }
-        return Box2D::new(
-            Point2D::new(min_x, min_y),
-            Point2D::new(max_x, max_y),
-        );
+fn translate_to<T: Coord>(points: &mut [Point2D<T>], dest: Point2D<T>) {
+    let centroid = centroid(points);
+    for point in points {
+        *point += dest - centroid;
     }
+}
 
-    fn translate_to(&self, dest: Point2D<PathCoord>) -> Path2D {
-        let centroid = self.centroid();
-        Path2D {
-            points: self.points
-                .iter()
-                .map(|point| *point + (dest - centroid))
-                .collect()
-        }
+/// Normalize resampled points in place, returning the transform that maps them back to
+/// their original coordinates.
+fn normalize<T: Coord>(points: &mut [Point2D<T>]) -> Alignment<T> {
+    let centroid = centroid(points);
+    let radians = indicative_angle(points);
+    rotate_by(points, -radians);
+    let bounds = bounding_rect(points);
+    let square_size = T::constant(SQUARE_SIZE);
+    scale_by(points, square_size);
+    translate_to(points, Point2D::default());
+    Alignment {
+        angle: T::zero(),
+        scale: (bounds.width() / square_size, bounds.height() / square_size),
+        rotation: radians,
+        mirror: Mirror::None,
+        translation: (centroid.x, centroid.y),
+    }
+}
+
+/// The margin by which a lower bound on a distance must exceed another distance to rule
+/// it out, covering rounding differences between the two computations.
+const BOUND_TOLERANCE: f64 = 1e-3;
+
+/// The number of independent sums used when adding up point distances, which lets the
+/// compiler evaluate several points at once.
+const LANES: usize = 8;
+
+/// A normalized path prepared for comparison against templates at many rotations. The
+/// coordinates are stored in flat arrays relative to the path's centroid, so rotating
+/// the path for each comparison needs no allocation.
+#[derive(Default)]
+struct Prepared<T> {
+    xs: Vec<T>,
+    ys: Vec<T>,
+    /// The distance of each point from the centroid.
+    radii: Vec<T>,
+    centroid: Point2D<T>,
+}
+
+impl<T: Coord> Prepared<T> {
+    fn new(points: &[Point2D<T>]) -> Prepared<T> {
+        let mut prepared = Prepared::default();
+        prepared.prepare(points);
+        prepared
     }
 
-    fn gss(&self, a: f32, b: f32, template: &Path2D) -> (f32, f32) {
-        let phi = 0.5f32 * (-1.0 + 5.0f32.sqrt());
-        let x = phi * a + (1.0 - phi) * b;
-        return (
-            x,
-            self.distance_at_angle(template, x),
-        );
+    /// Replace the contents of this buffer with the provided points.
+    fn prepare(&mut self, points: &[Point2D<T>]) {
+        self.centroid = centroid(points);
+        self.xs.clear();
+        self.ys.clear();
+        self.xs.extend(points.iter().map(|p| p.x - self.centroid.x));
+        self.ys.extend(points.iter().map(|p| p.y - self.centroid.y));
+        self.radii.clear();
+        self.radii.extend(self.xs.iter().zip(&self.ys).map(|(&x, &y)| (x * x + y * y).sqrt()));
     }
 
-    fn distance_at_best_angle(
+    fn gss(&self, a: T, b: T, template: &[Point2D<T>], bound: T) -> (T, T) {
+        let phi = T::constant(0.5) * (-T::one() + T::constant(5.0).sqrt());
+        let x = phi * a + (T::one() - phi) * b;
+        (x, self.distance_at_angle(template, x, bound))
+    }
+
+    /// Returns the rotation in radians at which this path best matches the template,
+    /// along with the distance between them at that rotation.
+    #[cfg(test)]
+    fn best_angle(
         &self,
-        template: &Path2D,
-        mut from_angle: f32,
-        mut to_angle: f32,
-        threshold: f32,
-    ) -> f32 {
-        let (mut x1, mut f1) = self.gss(from_angle, to_angle, template);
-        let (mut x2, mut f2) = self.gss(to_angle, from_angle, template);
+        template: &[Point2D<T>],
+        from_angle: T,
+        to_angle: T,
+        threshold: T,
+    ) -> (T, T) {
+        self.best_angle_below(template, from_angle, to_angle, threshold, T::infinity())
+            .unwrap_or((from_angle, T::max_value()))
+    }
+
+    /// Like [Prepared::best_angle], but returns `None` as soon as it is certain that the
+    /// distance at the best angle is not less than `bound`. When it returns a result, it
+    /// is identical to that of [Prepared::best_angle].
+    fn best_angle_below(
+        &self,
+        template: &[Point2D<T>],
+        mut from_angle: T,
+        mut to_angle: T,
+        threshold: T,
+        bound: T,
+    ) -> Option<(T, T)> {
+        // The distance at any angle is at least this much.
+        let lower_bound = self.distance_lower_bound(template);
+        let tolerance = T::constant(BOUND_TOLERANCE);
+        if lower_bound * (T::one() - tolerance) - tolerance >= bound {
+            return None;
+        }
+
+        // Each new distance is only compared against the one kept from the previous
+        // step, so it can be abandoned as soon as it is certain to be larger. An
+        // abandoned distance is always the one discarded by the next comparison.
+        let (mut x1, mut f1) = self.gss(from_angle, to_angle, template, T::infinity());
+        let (mut x2, mut f2) = self.gss(to_angle, from_angle, template, f1);
 
         while (to_angle - from_angle).abs() > threshold {
             if f1 < f2 {
                 to_angle = x2;
                 x2 = x1;
                 f2 = f1;
-                (x1, f1) = self.gss(from_angle, to_angle, template);
+                (x1, f1) = self.gss(from_angle, to_angle, template, f2);
             } else {
                 from_angle = x1;
                 x1 = x2;
                 f1 = f2;
-                (x2, f2) = self.gss(to_angle, from_angle, template);
+                (x2, f2) = self.gss(to_angle, from_angle, template, f1);
             }
         }
-        return f1.min(f2);
+        let best = if f1 < f2 { (x1, f1) } else { (x2, f2) };
+        (best.1 < bound).then_some(best)
     }
 
-    fn distance_at_angle(&self, template: &Path2D, radians: f32) -> f32 {
-        let rotated = self.rotate_by(radians);
-        return rotated.path_distance(&template);
+    /// Returns a lower bound on the distance between this path and the template at any
+    /// rotation. Rotating a point around the centroid does not change its distance from
+    /// the centroid, so each point is at least as far from its template point as the
+    /// difference between their distances from the centroid.
+    fn distance_lower_bound(&self, template: &[Point2D<T>]) -> T {
+        if self.radii.len() != template.len() {
+            return T::zero();
+        }
+        let centroid = self.centroid;
+        let difference = |radius: T, template: &Point2D<T>| {
+            let (dx, dy) = (template.x - centroid.x, template.y - centroid.y);
+            let template_radius = (dx * dx + dy * dy).sqrt();
+            (radius - template_radius).abs()
+        };
+        let radii = self.radii.chunks_exact(LANES);
+        let templates = template.chunks_exact(LANES);
+        let remainder: T = radii.remainder()
+            .iter()
+            .zip(templates.remainder())
+            .map(|(&radius, t)| difference(radius, t))
+            .sum();
+        let mut sums = [T::zero(); LANES];
+        for (radii, templates) in radii.zip(templates) {
+            for lane in 0..LANES {
+                sums[lane] += difference(radii[lane], &templates[lane]);
+            }
+        }
+        (sums.iter().copied().sum::<T>() + remainder) / T::from_count(self.radii.len())
     }
 
-    #[allow(non_snake_case)]
-    fn path_distance(&self, other: &Path2D) -> f32 {
-        if self.points.len() != other.points.len() {
-            return f32::MAX;
+    /// Returns the average distance between corresponding points of the template and
+    /// this path rotated by `radians` around its centroid. If the distance is certain to
+    /// be greater than `bound`, the sum may be abandoned early and infinity returned
+    /// instead.
+    fn distance_at_angle(
+        &self,
+        template: &[Point2D<T>],
+        radians: T,
+        bound: T,
+    ) -> T {
+        let len = self.xs.len();
+        if len != template.len() {
+            return T::max_value();
         }
-        let mut D = 0.0f32;
-        for (point_a, point_b) in self.points.iter().zip(other.points.iter()) {
-            D += point_b.distance_to(*point_a);
+        let (sin, cos) = radians.sin_cos();
+        let centroid = self.centroid;
+        let distance = |x: T, y: T, template: &Point2D<T>| {
+            let dx = template.x - (x * cos - y * sin + centroid.x);
+            let dy = template.y - (x * sin + y * cos + centroid.y);
+            (dx * dx + dy * dy).sqrt()
+        };
+
+        let xs = self.xs.chunks_exact(LANES);
+        let ys = self.ys.chunks_exact(LANES);
+        let templates = template.chunks_exact(LANES);
+        let remainder: T = xs.remainder()
+            .iter()
+            .zip(ys.remainder())
+            .zip(templates.remainder())
+            .map(|((&x, &y), t)| distance(x, y, t))
+            .sum();
+        let mut sums = [T::zero(); LANES];
+        for ((xs, ys), templates) in xs.zip(ys).zip(templates) {
+            for lane in 0..LANES {
+                sums[lane] += distance(xs[lane], ys[lane], &templates[lane]);
+            }
+            // Every term is positive, so the partial sum never exceeds the final sum.
+            if sums.iter().copied().sum::<T>() / T::from_count(len) > bound {
+                return T::infinity();
+            }
         }
-        return D / self.points.len() as f32;
+        (sums.iter().copied().sum::<T>() + remainder) / T::from_count(len)
     }
 }
 
 /// A normalized gesture template.
-pub struct Template {
+pub struct Template<T = PathCoord> {
     /// The name of this template.
     pub name: String,
     /// The 2d points that make up this gesture.
-    pub path: Path2D,
+    pub path: Path2D<T>,
+    /// Which variations of a path are considered when matching against this template.
+    pub invariance: Invariance,
+}
+
+/// Variations of a path that match a template equally well. By default, paths must be
+/// drawn in the same direction, from the same start point and with the same handedness
+/// as the template.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Invariance {
+    /// Paths may be drawn in the opposite direction.
+    pub direction: bool,
+    /// Closed paths may start at any point along the stroke.
+    pub start_point: bool,
+    /// Paths may be mirrored left to right.
+    pub mirror_horizontal: bool,
+    /// Paths may be mirrored top to bottom.
+    pub mirror_vertical: bool,
+}
+
+impl Invariance {
+    /// Returns true if no variations are allowed.
+    pub fn is_none(&self) -> bool {
+        *self == Invariance::default()
+    }
+
+    /// Returns the variations allowed by either this or `other`.
+    fn union(self, other: Invariance) -> Invariance {
+        Invariance {
+            direction: self.direction || other.direction,
+            start_point: self.start_point || other.start_point,
+            mirror_horizontal: self.mirror_horizontal || other.mirror_horizontal,
+            mirror_vertical: self.mirror_vertical || other.mirror_vertical,
+        }
+    }
+}
+
+/// A reflection of a path or template.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Mirror {
+    /// No reflection.
+    #[default]
+    None,
+    /// Reflected left to right, across a vertical axis.
+    Horizontal,
+    /// Reflected top to bottom, across a horizontal axis.
+    Vertical,
+}
+
+impl Mirror {
+    /// Reflect an offset from the center of the reflection.
+    fn apply<T: Coord>(self, x: T, y: T) -> (T, T) {
+        match self {
+            Mirror::None => (x, y),
+            Mirror::Horizontal => (-x, y),
+            Mirror::Vertical => (x, -y),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -227,29 +584,65 @@ pub enum TemplateError {
     PathEmpty,
 }
 
-impl Template {
+impl<T: Coord> Template<T> {
     /// Create a new normalized template from a path of arbitrary points.
     /// Returns an error if creation fails for any reason.
-    pub fn new(name: String, points: &Path2D) -> Result<Template, TemplateError> {
+    pub fn new(name: String, points: &Path2D<T>) -> Result<Template<T>, TemplateError> {
         if points.points.is_empty() {
             return Err(TemplateError::PathEmpty);
         }
 
-        let points = points.resample(NUM_POINTS);
-        let radians = points.indicative_angle();
-        let points = points.rotate_by(-radians);
-        let points = points.scale_by(SQUARE_SIZE);
-        let points = points.translate_to(Point2D::default());
+        let (points, _) = Template::normalize(points);
         Ok(Template {
             name,
             path: points,
+            invariance: Invariance::default(),
         })
     }
 
+    /// Returns this template, matching paths with the variations allowed by `invariance`.
+    pub fn with_invariance(mut self, invariance: Invariance) -> Template<T> {
+        self.invariance = invariance;
+        self
+    }
+
+    /// Returns a normalized copy of this template reflected by `mirror`, with the provided
+    /// name.
+    pub fn mirrored(&self, name: String, mirror: Mirror) -> Result<Template<T>, TemplateError> {
+        let template = Template::new(name, &self.path.mirrored(mirror))?;
+        Ok(template.with_invariance(self.invariance))
+    }
+
+    /// Normalize a non-empty path, returning the normalized path along with the
+    /// transform that maps it back to the original path's coordinates.
+    fn normalize(points: &Path2D<T>) -> (Path2D<T>, Alignment<T>) {
+        Template::normalize_resampled(points.resample(NUM_POINTS))
+    }
+
+    /// Like [Template::normalize], for a path that has already been resampled.
+    fn normalize_resampled(mut points: Path2D<T>) -> (Path2D<T>, Alignment<T>) {
+        let alignment = normalize(&mut points.points);
+        (points, alignment)
+    }
+
+    /// Returns this template's path transformed into the coordinate frame of the path
+    /// that `alignment` was found for, so that it can be drawn in place of that path.
+    pub fn aligned_path(&self, alignment: &Alignment<T>) -> Path2D<T> {
+        let mut aligned = Path2D::default();
+        for point in &self.path.points {
+            let (x, y) = alignment.transform_point(point.x, point.y);
+            aligned.push(x, y);
+        }
+        aligned
+    }
+
     /// Create a new template from a path of previously-normalized points.
     /// This should only be used to create templates based on previously-constructed
     /// template data (eg. deserializing guesture template data).
-    pub fn new_from_template(name: String, points: Path2D) -> Result<Template, TemplateError> {
+    pub fn new_from_template(
+        name: String,
+        points: Path2D<T>,
+    ) -> Result<Template<T>, TemplateError> {
         if points.points.is_empty() {
             return Err(TemplateError::PathEmpty);
         }
@@ -257,10 +650,27 @@ impl Template {
         Ok(Template {
             name,
             path: points,
+            invariance: Invariance::default(),
         })
     }
 }
 
+/// Returns a mirrored copy of each template, named with `suffix` appended to the original
+/// name. With an empty suffix, adding the copies to a set makes it tolerant of mirrored
+/// drawings; with a distinct suffix, mirrored drawings are recognized as separate gestures.
+pub fn mirror_templates<T: Coord>(
+    templates: &[Template<T>],
+    mirror: Mirror,
+    suffix: &str,
+) -> Vec<Template<T>> {
+    templates
+        .iter()
+        .filter_map(|template| {
+            template.mirrored(format!("{}{}", template.name, suffix), mirror).ok()
+        })
+        .collect()
+}
+
 #[derive(Debug)]
 pub enum Error {
     /// The provided path was too short to complete the match.
@@ -269,6 +679,46 @@ pub enum Error {
     NoMatch,
 }
 
+/// The transform that aligns a normalized template with a path that matched it. A point
+/// on the template is mapped into the path's coordinates by rotating it by `-angle`
+/// around the origin, scaling it by `scale`, rotating it by `rotation` around the origin,
+/// reflecting it by `mirror`, and finally translating it by `translation`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Alignment<T = PathCoord> {
+    /// The rotation in radians at which the normalized path best matched the template.
+    pub angle: T,
+    /// The horizontal and vertical scale from normalized units to path units.
+    pub scale: (T, T),
+    /// The indicative angle of the path in radians, which normalization removed.
+    pub rotation: T,
+    /// The reflection of the path that matched the template.
+    pub mirror: Mirror,
+    /// The centroid of the path.
+    pub translation: (T, T),
+}
+
+impl<T: Coord> Alignment<T> {
+    /// Map a point from normalized template coordinates into path coordinates.
+    pub fn transform_point(&self, x: T, y: T) -> (T, T) {
+        let (sin, cos) = (-self.angle).sin_cos();
+        let (x, y) = (x * cos - y * sin, x * sin + y * cos);
+        let (x, y) = (x * self.scale.0, y * self.scale.1);
+        let (sin, cos) = self.rotation.sin_cos();
+        let (x, y) = (x * cos - y * sin, x * sin + y * cos);
+        let (x, y) = self.mirror.apply(x, y);
+        (x + self.translation.0, y + self.translation.1)
+    }
+}
+
+/// Convert an average point distance between two normalized paths into a score between
+/// 0.0 and 1.0, where 1.0 is an exact match.
+fn score_for_distance<T: Coord>(distance: T) -> T {
+    let square_size = T::constant(SQUARE_SIZE);
+    let diagonal = (T::constant(2.0) * square_size * square_size).sqrt();
+    let half_diagonal = T::constant(0.5) * diagonal;
+    T::one() - distance / half_diagonal
+}
+
 /// Given a set of templates and a path, returns the template that is the closest match.
 /// A score between 0.0 and 1.0 is returned along with the matching template; the closer
 /// to 1.0, the more exact the match. Returns an error if the matching process failed for
@@ -276,11 +726,11 @@ pub enum Error {
 ///
 /// Defaults to matching paths within a 90 degree range (-45 to 45) with 2 degree precision
 /// of the original template.
-pub fn find_matching_template_with_defaults<'a, 'b>(
-    templates: &'a [Template],
-    path: &'b Path2D,
-) -> Result<(&'a Template, f32), Error> {
-    return find_matching_template(templates, path, 45.0, 2.0);
+pub fn find_matching_template_with_defaults<'a, 'b, T: Coord>(
+    templates: &'a [Template<T>],
+    path: &'b Path2D<T>,
+) -> Result<(&'a Template<T>, T), Error> {
+    return find_matching_template(templates, path, T::constant(45.0), T::constant(2.0));
 }
 
 /// Given a set of templates and a path, returns the template that is the closest match.
@@ -291,41 +741,356 @@ pub fn find_matching_template_with_defaults<'a, 'b>(
 /// Provides more configution options for the matching process. `angle_range` determines
 /// the range of rotation in degrees in which a path is compared against each template.
 /// `angle_precision` controls the precision at which rotations will be attmpted.
-pub fn find_matching_template<'a, 'b>(
-    templates: &'a [Template],
-    path: &'b Path2D,
-    angle_range: f32,
-    angle_precision: f32,
-) -> Result<(&'a Template, f32), Error> {
-    if path.points.len() < 2 || path.length() < 100.0 {
+pub fn find_matching_template<'a, 'b, T: Coord>(
+    templates: &'a [Template<T>],
+    path: &'b Path2D<T>,
+    angle_range: T,
+    angle_precision: T,
+) -> Result<(&'a Template<T>, T), Error> {
+    let mut scratch = MatchScratch::default();
+    find_matching_template_with_scratch(templates, path, angle_range, angle_precision, &mut scratch)
+}
+
+/// Reusable working memory for matching paths against templates. Passing the same
+/// scratch buffer to every call of [find_matching_template_with_scratch] avoids
+/// allocating while matching.
+#[derive(Default)]
+pub struct MatchScratch<T = PathCoord> {
+    points: Vec<Point2D<T>>,
+    prepared: Prepared<T>,
+}
+
+/// Like [find_matching_template], but stores the normalized path in `scratch` instead of
+/// allocating. Templates with an [Invariance] require several normalized variants of the
+/// path, which are still allocated.
+pub fn find_matching_template_with_scratch<'a, T: Coord>(
+    templates: &'a [Template<T>],
+    path: &Path2D<T>,
+    angle_range: T,
+    angle_precision: T,
+    scratch: &mut MatchScratch<T>,
+) -> Result<(&'a Template<T>, T), Error> {
+    if templates.iter().any(|t| !t.invariance.is_none()) {
+        return find_matching_template_with_alignment(templates, path, angle_range, angle_precision)
+            .map(|(template, score, _)| (template, score));
+    }
+    if path.points.len() < 2 || path.length() < T::constant(100.0) {
         return Err(Error::TooShort);
     }
 
-    let diagonal = (2.0f32 * SQUARE_SIZE * SQUARE_SIZE).sqrt();
-    let half_diagonal = 0.5f32 * diagonal;
+    resample_into(&path.points, NUM_POINTS, &mut scratch.points);
+    normalize(&mut scratch.points);
+    scratch.prepared.prepare(&scratch.points);
+    let candidate = &scratch.prepared;
 
-    let candidate = match Template::new("".to_owned(), path) {
-        Ok(template) => template,
-        Err(TemplateError::PathEmpty) => unreachable!(),
-    };
+    let angle_range: T = Angle::degrees(angle_range).get();
+    let angle_precision: T = Angle::degrees(angle_precision).get();
+    let best = best_template(templates, &|_, template: &Template<T>, bound| {
+        let (_, distance) = candidate.best_angle_below(
+            &template.path.points,
+            -angle_range,
+            angle_range,
+            angle_precision,
+            bound,
+        )?;
+        Some((distance, ()))
+    });
+    let (index, distance, ()) = best.ok_or(Error::NoMatch)?;
+    Ok((&templates[index], score_for_distance(distance)))
+}
 
-    let angle_range: f32 = Angle::degrees(angle_range).get();
-    let angle_precision: f32 = Angle::degrees(angle_precision).get();
-    let mut template_match = Err(Error::NoMatch);
-    let mut best_distance = f32::MAX;
-    for template in templates {
-        let distance = candidate.path.distance_at_best_angle(
-            &template.path,
+/// Like [find_matching_template], but also returns the transform that aligns the matching
+/// template with the path. Use [Template::aligned_path] to replace the path with a clean
+/// copy of the template drawn in the same place.
+pub fn find_matching_template_with_alignment<'a, T: Coord>(
+    templates: &'a [Template<T>],
+    path: &Path2D<T>,
+    angle_range: T,
+    angle_precision: T,
+) -> Result<(&'a Template<T>, T, Alignment<T>), Error> {
+    find_matching_template_where(templates, path, angle_range, angle_precision, &|_| true)
+}
+
+/// Like [find_matching_template_with_alignment], but only considers the templates for
+/// which `include` returns true, given their index.
+fn find_matching_template_where<'a, T: Coord>(
+    templates: &'a [Template<T>],
+    path: &Path2D<T>,
+    angle_range: T,
+    angle_precision: T,
+    include: &(dyn Fn(usize) -> bool + Sync),
+) -> Result<(&'a Template<T>, T, Alignment<T>), Error> {
+    if path.points.len() < 2 || path.length() < T::constant(100.0) {
+        return Err(Error::TooShort);
+    }
+
+    let resampled = path.resample(NUM_POINTS);
+    let invariance = templates
+        .iter()
+        .enumerate()
+        .filter(|&(index, _)| include(index))
+        .fold(Invariance::default(), |all, (_, t)| all.union(t.invariance));
+    let variants = Variant::all(&resampled, invariance);
+
+    let angle_range: T = Angle::degrees(angle_range).get();
+    let angle_precision: T = Angle::degrees(angle_precision).get();
+    let best = best_template(templates, &|index, template: &Template<T>, bound| {
+        if !include(index) {
+            return None;
+        }
+        measure_variants(&variants, template, angle_range, angle_precision, bound)
+    });
+    let (index, distance, alignment) = best.ok_or(Error::NoMatch)?;
+    Ok((&templates[index], score_for_distance(distance), alignment))
+}
+
+/// Given a set of templates and a path, returns the indices of the `count` templates that
+/// most closely match the path, best first, along with their scores. Equally close
+/// templates are kept in order. The path is normalized once for all of the templates, so
+/// this is much faster than matching it against each template in turn. Returns an error if
+/// the path is too short, or if no template could be compared with it.
+///
+/// `angle_range` and `angle_precision` are in degrees, as in [find_matching_template].
+pub fn find_matching_templates<T: Coord>(
+    templates: &[Template<T>],
+    path: &Path2D<T>,
+    angle_range: T,
+    angle_precision: T,
+    count: usize,
+) -> Result<Vec<(usize, T)>, Error> {
+    if path.points.len() < 2 || path.length() < T::constant(100.0) {
+        return Err(Error::TooShort);
+    }
+
+    let resampled = path.resample(NUM_POINTS);
+    let invariance = templates
+        .iter()
+        .fold(Invariance::default(), |all, t| all.union(t.invariance));
+    let variants = Variant::all(&resampled, invariance);
+
+    let angle_range: T = Angle::degrees(angle_range).get();
+    let angle_precision: T = Angle::degrees(angle_precision).get();
+    let best = best_templates(
+        templates,
+        &|_, template: &Template<T>, bound| {
+            measure_variants(&variants, template, angle_range, angle_precision, bound)
+        },
+        count,
+    );
+    if best.is_empty() && count > 0 {
+        return Err(Error::NoMatch);
+    }
+    Ok(best
+        .into_iter()
+        .map(|(index, distance, _)| (index, score_for_distance(distance)))
+        .collect())
+}
+
+/// Returns the distance between the template and the closest of the variants it allows,
+/// along with the alignment of that variant, or `None` if no variant is closer than
+/// `bound`. `angle_range` and `angle_precision` are in radians.
+fn measure_variants<T: Coord>(
+    variants: &[Variant<T>],
+    template: &Template<T>,
+    angle_range: T,
+    angle_precision: T,
+    mut bound: T,
+) -> Option<(T, Alignment<T>)> {
+    let mut best = None;
+    for variant in variants.iter().filter(|v| v.allowed_by(template.invariance)) {
+        let Some((angle, distance)) = variant.path.best_angle_below(
+            &template.path.points,
             -angle_range,
             angle_range,
             angle_precision,
-        );
-        if distance < best_distance {
-            best_distance = distance;
-            template_match = Ok((template, 1.0 - best_distance / half_diagonal));
+            bound,
+        ) else {
+            continue
+        };
+        bound = distance;
+        best = Some((distance, Alignment { angle, ..variant.alignment }));
+    }
+    best
+}
+
+/// Measures the distance between a path and the template at an index, returning `None` if
+/// it is not less than the provided bound.
+type Measure<'a, T, E> = dyn Fn(usize, &Template<T>, T) -> Option<(T, E)> + Sync + 'a;
+
+/// The number of templates searched by each parallel task.
+#[cfg(feature = "rayon")]
+const PARALLEL_CHUNK_SIZE: usize = 64;
+
+/// Returns the index of the template with the smallest distance according to `measure`,
+/// along with that distance and any extra result of the measurement. If several templates
+/// are equally close, the first one is returned.
+fn best_template<T: Coord, E: Send>(
+    templates: &[Template<T>],
+    measure: &Measure<T, E>,
+) -> Option<(usize, T, E)> {
+    best_templates(templates, measure, 1).pop()
+}
+
+/// Returns the `count` templates with the smallest distances according to `measure`,
+/// closest first, along with their indices and the results of their measurements.
+/// Equally close templates are kept in order.
+///
+/// With the `rayon` feature, runs of templates are searched in parallel and their results
+/// combined so that the outcome is identical to searching them in order.
+fn best_templates<T: Coord, E: Send>(
+    templates: &[Template<T>],
+    measure: &Measure<T, E>,
+    count: usize,
+) -> Vec<(usize, T, E)> {
+    #[cfg(feature = "rayon")]
+    {
+        use rayon::prelude::*;
+        use core::sync::atomic::{AtomicU64, Ordering};
+
+        // The smallest distance found by any task so far, which bounds the search for a
+        // single template.
+        let shared = AtomicU64::new(T::max_value().to_ordered_bits());
+        let measure = |index: usize, template: &Template<T>, bound: T| {
+            if count > 1 {
+                return measure(index, template, bound);
+            }
+            // A template exactly as close as one found by another task may still come
+            // before it, so only templates that are further away are skipped.
+            let shared_bound = T::from_ordered_bits(shared.load(Ordering::Relaxed) + 1);
+            let (distance, extra) = measure(index, template, bound.min(shared_bound))?;
+            shared.fetch_min(distance.to_ordered_bits(), Ordering::Relaxed);
+            Some((distance, extra))
+        };
+        let mut best: Vec<_> = templates
+            .par_chunks(PARALLEL_CHUNK_SIZE)
+            .enumerate()
+            .flat_map_iter(|(chunk, templates)| {
+                let offset = chunk * PARALLEL_CHUNK_SIZE;
+                let measure = |index, t: &Template<T>, bound| measure(offset + index, t, bound);
+                best_templates_in(templates, &measure, count)
+                    .into_iter()
+                    .map(move |(index, distance, extra)| (offset + index, distance, extra))
+            })
+            .collect();
+        best.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+        best.truncate(count);
+        best
+    }
+    #[cfg(not(feature = "rayon"))]
+    best_templates_in(templates, measure, count)
+}
+
+fn best_templates_in<T: Coord, E>(
+    templates: &[Template<T>],
+    measure: &Measure<T, E>,
+    count: usize,
+) -> Vec<(usize, T, E)> {
+    let mut best: Vec<(usize, T, E)> = Vec::with_capacity(count + 1);
+    if count == 0 {
+        return best;
+    }
+    for (index, template) in templates.iter().enumerate() {
+        let bound = if best.len() < count { T::max_value() } else { best[count - 1].1 };
+        if let Some((distance, extra)) = measure(index, template, bound) {
+            let position = best.partition_point(|b| b.1 <= distance);
+            best.insert(position, (index, distance, extra));
+            best.truncate(count);
+        }
+    }
+    best
+}
+
+/// A normalized reflection or reordering of the points of a path.
+struct Variant<T> {
+    reversed: bool,
+    shifted: bool,
+    mirror: Mirror,
+    path: Prepared<T>,
+    alignment: Alignment<T>,
+}
+
+impl<T: Coord> Variant<T> {
+    /// Returns the variations of the resampled path allowed by `invariance`. The
+    /// unmodified path is first.
+    fn all(resampled: &Path2D<T>, invariance: Invariance) -> Vec<Variant<T>> {
+        let mut mirrors = vec![Mirror::None];
+        if invariance.mirror_horizontal {
+            mirrors.push(Mirror::Horizontal);
+        }
+        if invariance.mirror_vertical {
+            mirrors.push(Mirror::Vertical);
+        }
+        let closed = invariance.start_point && resampled.is_closed(T::constant(CLOSED_GAP.into()));
+
+        let mut variants = vec![];
+        for mirror in mirrors {
+            let mirrored = resampled.mirrored(mirror);
+            let mut directions = vec![(mirrored.clone(), false)];
+            if invariance.direction {
+                let mut reversed = mirrored;
+                reversed.points.reverse();
+                directions.push((reversed, true));
+            }
+
+            for (path, reversed) in directions {
+                variants.push(Variant::new(path.clone(), reversed, false, mirror));
+                if !closed {
+                    continue;
+                }
+                // The last point closes the loop, so it moves along with the first.
+                let loop_points = &path.points[..path.points.len() - 1];
+                let mut starts: Vec<usize> = (1..START_POINTS)
+                    .map(|i| i * loop_points.len() / START_POINTS)
+                    .filter(|&start| start > 0)
+                    .collect();
+                starts.dedup();
+                for start in starts {
+                    let mut points = loop_points[start..].to_vec();
+                    points.extend_from_slice(&loop_points[..start]);
+                    points.push(points[0]);
+                    variants.push(Variant::new(Path2D { points }, reversed, true, mirror));
+                }
+            }
+        }
+        variants
+    }
+
+    fn new(resampled: Path2D<T>, reversed: bool, shifted: bool, mirror: Mirror) -> Variant<T> {
+        let (path, alignment) = Template::normalize_resampled(resampled);
+        Variant {
+            reversed,
+            shifted,
+            mirror,
+            path: Prepared::new(&path.points),
+            alignment: Alignment { mirror, ..alignment },
         }
     }
-    return template_match;
+
+    fn allowed_by(&self, invariance: Invariance) -> bool {
+        let mirror_allowed = match self.mirror {
+            Mirror::None => true,
+            Mirror::Horizontal => invariance.mirror_horizontal,
+            Mirror::Vertical => invariance.mirror_vertical,
+        };
+        mirror_allowed &&
+            (!self.reversed || invariance.direction) &&
+            (!self.shifted || invariance.start_point)
+    }
+}
+
+#[cfg(any(feature = "svg", feature = "xml"))]
+fn escape_xml(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
 }
 
 #[cfg(test)]
@@ -335,4 +1100,194 @@ mod tests {
     #[test]
     fn it_works() {
     }
+
+    #[test]
+    fn closed_paths_match_from_any_start() {
+        let square = |corners: &[(f32, f32)]| {
+            let mut path = Path2D::default();
+            for (j, &(x0, y0)) in corners.iter().enumerate() {
+                let (x1, y1) = corners[(j + 1) % corners.len()];
+                for i in 0..10 {
+                    let t = i as f32 / 10.0;
+                    path.push(x0 + (x1 - x0) * t, y0 + (y1 - y0) * t);
+                }
+            }
+            path.push(corners[0].0, corners[0].1);
+            path
+        };
+        let corners = [(0., 0.), (100., 0.), (100., 100.), (0., 100.)];
+        let mut templates = [Template::new("square".to_owned(), &square(&corners)).unwrap()];
+        // Started partway along the right edge.
+        let drawn = square(&[(100., 30.), (100., 100.), (0., 100.), (0., 0.), (100., 0.)]);
+
+        let (_, score) = find_matching_template(&templates, &drawn, 45.0, 2.0).unwrap();
+        assert!(score < 0.9);
+
+        templates[0].invariance = Invariance { start_point: true, ..Invariance::default() };
+        let (_, score) = find_matching_template(&templates, &drawn, 45.0, 2.0).unwrap();
+        assert!(score > 0.95);
+
+        // Clockwise rather than counter-clockwise.
+        let reversed = square(&[(0., 0.), (0., 100.), (100., 100.), (100., 0.)]);
+        let (_, score) = find_matching_template(&templates, &reversed, 45.0, 2.0).unwrap();
+        assert!(score < 0.9);
+        templates[0].invariance.direction = true;
+        let (_, score) = find_matching_template(&templates, &reversed, 45.0, 2.0).unwrap();
+        assert!(score > 0.95);
+
+        // Every direction is tried from the original start and START_POINTS - 1 others.
+        let resampled = square(&corners).resample(NUM_POINTS);
+        let variants = Variant::all(&resampled, templates[0].invariance);
+        assert_eq!(variants.len(), 2 * START_POINTS);
+        assert_eq!(variants.iter().filter(|v| v.shifted).count(), 2 * (START_POINTS - 1));
+    }
+
+    #[test]
+    fn mirrored_matching() {
+        // A hook that curls to the right, and the same hook drawn left-handed.
+        let mut hook = Path2D::default();
+        let mut mirrored = Path2D::default();
+        for &(x, y) in &[(0., 0.), (0., 150.), (80., 150.), (80., 100.)] {
+            hook.push(x, y);
+            mirrored.push(-x, y);
+        }
+        let hook = hook.resample(40);
+        let mirrored = mirrored.resample(40);
+        let mut templates = vec![Template::new("hook".to_owned(), &hook).unwrap()];
+        let (_, score, _) =
+            find_matching_template_with_alignment(&templates, &mirrored, 45.0, 2.0).unwrap();
+        assert!(score < 0.9);
+
+        templates[0].invariance = Invariance { mirror_horizontal: true, ..Invariance::default() };
+        let (_, score, alignment) =
+            find_matching_template_with_alignment(&templates, &mirrored, 45.0, 2.0).unwrap();
+        assert!(score > 0.95);
+        assert_eq!(alignment.mirror, Mirror::Horizontal);
+
+        // Mirrored copies with a distinct name keep the two hands apart.
+        templates[0].invariance = Invariance::default();
+        let left = mirror_templates(&templates, Mirror::Horizontal, "-left");
+        templates.extend(left);
+        let (template, _, alignment) =
+            find_matching_template_with_alignment(&templates, &mirrored, 45.0, 2.0).unwrap();
+        assert_eq!(template.name, "hook-left");
+        assert_eq!(alignment.mirror, Mirror::None);
+    }
+
+    #[test]
+    fn pruning_matches_exhaustive_search() {
+        use crate::augment::{Augmentation, Augmenter};
+
+        let mut shapes = vec![];
+        let outlines = [[(0., 0.), (100., 0.), (100., 100.)], [(0., 0.), (50., 100.), (100., 0.)]];
+        for corners in outlines {
+            let mut path = Path2D::default();
+            for (x, y) in corners {
+                path.push(x, y);
+            }
+            shapes.push(Template::new("shape".to_owned(), &path.resample(30)).unwrap());
+        }
+        let mut augmenter = Augmenter::new(Augmentation::default(), 7);
+        let templates = augmenter.expand_templates(&shapes, 50);
+
+        let mut scratch = MatchScratch::default();
+        for drawn in augmenter.variants(&shapes[1].path, 10) {
+            let drawn = drawn.scaled(100.0, 100.0);
+            let mut candidate = drawn.resample(NUM_POINTS).points;
+            normalize(&mut candidate);
+            let candidate = Prepared::new(&candidate);
+            let range = Angle::degrees(45.0f32).get();
+            let precision = Angle::degrees(2.0f32).get();
+            let mut ranked: Vec<(usize, f32)> = templates
+                .iter()
+                .map(|template| {
+                    candidate.best_angle(&template.path.points, -range, range, precision).1
+                })
+                .enumerate()
+                .collect();
+            ranked.sort_by(|a, b| a.1.total_cmp(&b.1));
+            let (index, distance) = ranked[0];
+
+            let (template, score) =
+                find_matching_template_with_scratch(&templates, &drawn, 45.0, 2.0, &mut scratch)
+                    .unwrap();
+            assert!(std::ptr::eq(template, &templates[index]));
+            assert_eq!(score, score_for_distance(distance));
+
+            let best = find_matching_templates(&templates, &drawn, 45.0, 2.0, 5).unwrap();
+            let expected: Vec<_> = ranked[..5]
+                .iter()
+                .map(|&(index, distance)| (index, score_for_distance(distance)))
+                .collect();
+            assert_eq!(best, expected);
+        }
+    }
+
+    #[test]
+    fn ties_match_first_template() {
+        let mut path = Path2D::default();
+        for &(x, y) in &[(0., 0.), (100., 0.), (100., 100.)] {
+            path.push(x, y);
+        }
+        let path = path.resample(30);
+        let templates: Vec<_> = (0..300)
+            .map(|i| Template::new(i.to_string(), &path).unwrap())
+            .collect();
+        let (template, _) = find_matching_template(&templates, &path, 45.0, 2.0).unwrap();
+        assert_eq!(template.name, "0");
+        let (template, _) = find_matching_template(&templates[150..], &path, 45.0, 2.0).unwrap();
+        assert_eq!(template.name, "150");
+        let best = find_matching_templates(&templates, &path, 45.0, 2.0, 3).unwrap();
+        assert_eq!(best.iter().map(|b| b.0).collect::<Vec<_>>(), [0, 1, 2]);
+    }
+
+    #[test]
+    fn f64_paths_far_from_origin() {
+        // Far enough from the origin that single precision rounds coordinates to
+        // multiples of 32, which is a tenth of the gesture's size.
+        let origin = (450_000_000.0, 600_000_000.0);
+        let shape = |scale: f64, sign: f64| {
+            let mut path: Path2D<f64> = Path2D::default();
+            for i in 0..=40 {
+                let t = i as f64 / 40.0;
+                let y = sign * (t * 5.0).sin() * 100.0;
+                path.push(origin.0 + t * 300.0 * scale, origin.1 + y * scale);
+            }
+            path
+        };
+        let templates = [
+            Template::new("up".to_owned(), &shape(1.0, 1.0)).unwrap(),
+            Template::new("down".to_owned(), &shape(1.0, -1.0)).unwrap(),
+        ];
+        let (template, score) =
+            find_matching_template_with_defaults(&templates, &shape(1.5, -1.0)).unwrap();
+        assert_eq!(template.name, "down");
+        assert!(score > 0.99, "{}", score);
+    }
+
+    #[test]
+    fn alignment_maps_template_to_input() {
+        let mut shape = Path2D::default();
+        for &(x, y) in &[(0., 0.), (100., 0.), (100., 60.), (30., 60.)] {
+            shape.push(x, y);
+        }
+        let templates = [Template::new("hook".to_owned(), &shape.resample(40)).unwrap()];
+
+        // The same shape, rotated, scaled and moved.
+        let (sin, cos) = 0.3f32.sin_cos();
+        let mut drawn = Path2D::default();
+        for point in &shape.resample(40).points {
+            let (x, y) = (point.x * 2.0, point.y * 2.0);
+            drawn.push(x * cos - y * sin + 400.0, x * sin + y * cos - 50.0);
+        }
+
+        let (template, score, alignment) =
+            find_matching_template_with_alignment(&templates, &drawn, 45.0, 2.0).unwrap();
+        assert!(score > 0.95);
+        let aligned = template.aligned_path(&alignment);
+        let resampled = drawn.resample(NUM_POINTS);
+        let distance = Prepared::new(&aligned.points)
+            .distance_at_angle(&resampled.points, 0.0, f32::INFINITY);
+        assert!(distance < 5.0, "{}", distance);
+    }
 }
@@ -0,0 +1,493 @@
+//! Recognition of simple geometric shapes by fitting them directly to a path.
+//!
+//! Template matching handles some simple shapes poorly: straight lines cannot be scaled
+//! to a square, and closed shapes such as circles depend on where they were started.
+//! Instead, [recognize_shape] fits lines, circles and ellipses with least squares and
+//! fits polygons to the corners of the path, and reports the best fitting shape along
+//! with its parameters.
+
+use crate::{Coord, Error, Path2D, PathCoord, Point2D, CLOSED_GAP, NUM_POINTS};
+use euclid::default::Vector2D;
+use num_traits::Float;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// A point in a fitted shape.
+pub type Point<T = PathCoord> = (T, T);
+
+/// The lowest score at which a shape is reported.
+const MIN_SCORE: f64 = 0.95;
+
+/// The smallest ratio of a stroke's chord to its length for it to be a line.
+const LINE_STRAIGHTNESS: f64 = 0.95;
+
+/// The smallest ratio of an ellipse's minor radius to its major radius for it to be
+/// reported as a circle.
+const ROUND_RATIO: f64 = 0.8;
+
+/// How far in degrees the angles of a rectangle may be from a right angle.
+const RIGHT_ANGLE_TOLERANCE: f64 = 25.0;
+
+/// How far in degrees two edges may be from parallel to be merged into one.
+const COLLINEAR_TOLERANCE: f64 = 20.0;
+
+/// A geometric shape fitted to a path.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shape<T = PathCoord> {
+    /// A straight line.
+    Line {
+        /// The end of the line nearest the start of the path.
+        start: Point<T>,
+        /// The end of the line nearest the end of the path.
+        end: Point<T>,
+    },
+    /// A circle.
+    Circle {
+        /// The center of the circle.
+        center: Point<T>,
+        /// The radius of the circle.
+        radius: T,
+    },
+    /// An ellipse.
+    Ellipse {
+        /// The center of the ellipse.
+        center: Point<T>,
+        /// The radii along the major and minor axes, largest first.
+        radii: (T, T),
+        /// The direction of the major axis, in radians.
+        rotation: T,
+    },
+    /// A quadrilateral with roughly right angles.
+    Rectangle {
+        /// The corners of the rectangle, in drawing order.
+        corners: [Point<T>; 4],
+    },
+    /// A triangle.
+    Triangle {
+        /// The corners of the triangle, in drawing order.
+        corners: [Point<T>; 3],
+    },
+    /// A straight shaft followed by an arrowhead.
+    Arrow {
+        /// The start of the shaft.
+        tail: Point<T>,
+        /// The tip of the arrowhead, at the end of the shaft.
+        head: Point<T>,
+    },
+}
+
+impl<T> Shape<T> {
+    /// A name for this kind of shape, suitable for use alongside template names.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Shape::Line { .. } => "line",
+            Shape::Circle { .. } => "circle",
+            Shape::Ellipse { .. } => "ellipse",
+            Shape::Rectangle { .. } => "rectangle",
+            Shape::Triangle { .. } => "triangle",
+            Shape::Arrow { .. } => "arrow",
+        }
+    }
+}
+
+/// A shape recognized in a path.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShapeMatch<T = PathCoord> {
+    /// The fitted shape, in the coordinates of the path.
+    pub shape: Shape<T>,
+    /// How closely the path follows the shape: 1.0 minus the mean distance between them,
+    /// relative to the size of the path.
+    pub score: T,
+}
+
+impl<T: Coord> Path2D<T> {
+    /// Returns true if the ends of this path are within `tolerance` of each other,
+    /// relative to the length of the path.
+    pub fn is_closed(&self, tolerance: T) -> bool {
+        let length = self.length();
+        if self.points.len() < 3 || length == T::zero() {
+            return false;
+        }
+        let gap = self.points[0].distance_to(self.points[self.points.len() - 1]);
+        gap <= tolerance * length
+    }
+
+    /// Returns the ends of the least-squares line through this path, projected from the
+    /// first and last points of the path.
+    pub fn fit_line(&self) -> Option<(Point<T>, Point<T>)> {
+        let (center, direction) = self.principal_axis()?;
+        let project = |p: Point2D<T>| center + direction * (p - center).dot(direction);
+        let start = project(self.points[0]);
+        let end = project(self.points[self.points.len() - 1]);
+        Some(((start.x, start.y), (end.x, end.y)))
+    }
+
+    /// Returns the center and radius of the least-squares circle through this path.
+    pub fn fit_circle(&self) -> Option<(Point<T>, T)> {
+        if self.points.len() < 3 {
+            return None;
+        }
+        // Fit x² + y² + Dx + Ey + F = 0 relative to the centroid, for stability.
+        let origin = self.centroid();
+        let mut m = [[T::zero(); 3]; 3];
+        let mut rhs = [T::zero(); 3];
+        for point in &self.points {
+            let (x, y) = (point.x - origin.x, point.y - origin.y);
+            let z = x * x + y * y;
+            let row = [x, y, T::one()];
+            for i in 0..3 {
+                for j in 0..3 {
+                    m[i][j] += row[i] * row[j];
+                }
+                rhs[i] -= row[i] * z;
+            }
+        }
+        let [d, e, f] = solve3(m, rhs)?;
+        let two = T::constant(2.0);
+        let radius_squared = (d * d + e * e) / T::constant(4.0) - f;
+        if radius_squared <= T::zero() {
+            return None;
+        }
+        Some(((origin.x - d / two, origin.y - e / two), radius_squared.sqrt()))
+    }
+
+    /// Returns the center, radii and rotation in radians of an ellipse fitted to this
+    /// path. The axes are the principal axes of the path's points, and the radii are
+    /// fitted along them with least squares. The first radius is the larger.
+    pub fn fit_ellipse(&self) -> Option<(Point<T>, (T, T), T)> {
+        let (center, major) = self.principal_axis()?;
+        let minor = Vector2D::new(-major.y, major.x);
+        let (mut uuuu, mut uuvv, mut vvvv) = (T::zero(), T::zero(), T::zero());
+        let (mut uu, mut vv) = (T::zero(), T::zero());
+        for point in &self.points {
+            let offset = *point - center;
+            let u2 = offset.dot(major).powi(2);
+            let v2 = offset.dot(minor).powi(2);
+            uuuu += u2 * u2;
+            uuvv += u2 * v2;
+            vvvv += v2 * v2;
+            uu += u2;
+            vv += v2;
+        }
+        // Minimize the sum of (pu² + qv² - 1)², where p = 1/a² and q = 1/b².
+        let det = uuuu * vvvv - uuvv * uuvv;
+        if det == T::zero() {
+            return None;
+        }
+        let p = (uu * vvvv - vv * uuvv) / det;
+        let q = (vv * uuuu - uu * uuvv) / det;
+        if p <= T::zero() || q <= T::zero() {
+            return None;
+        }
+        let (a, b) = (p.sqrt().recip(), q.sqrt().recip());
+        let rotation = major.y.atan2(major.x);
+        let (radii, rotation) = if a >= b {
+            ((a, b), rotation)
+        } else {
+            ((b, a), rotation + T::constant(core::f64::consts::FRAC_PI_2))
+        };
+        Some(((center.x, center.y), radii, rotation))
+    }
+
+    /// Returns the centroid of this path and the direction of greatest variance of its
+    /// points.
+    fn principal_axis(&self) -> Option<(Point2D<T>, Vector2D<T>)> {
+        if self.points.len() < 2 {
+            return None;
+        }
+        let center = self.centroid();
+        let (mut xx, mut xy, mut yy) = (T::zero(), T::zero(), T::zero());
+        for point in &self.points {
+            let offset = *point - center;
+            xx += offset.x * offset.x;
+            xy += offset.x * offset.y;
+            yy += offset.y * offset.y;
+        }
+        let angle = T::constant(0.5) * (T::constant(2.0) * xy).atan2(xx - yy);
+        Some((center, Vector2D::new(Float::cos(angle), Float::sin(angle))))
+    }
+}
+
+/// Solve a 3x3 linear system with Cramer's rule.
+fn solve3<T: Coord>(m: [[T; 3]; 3], rhs: [T; 3]) -> Option<[T; 3]> {
+    let det = |m: [[T; 3]; 3]| {
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1]) -
+            m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0]) +
+            m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    };
+    let denominator = det(m);
+    if denominator.abs() < T::epsilon() {
+        return None;
+    }
+    let mut solution = [T::zero(); 3];
+    for (column, value) in solution.iter_mut().enumerate() {
+        let mut replaced = m;
+        for row in 0..3 {
+            replaced[row][column] = rhs[row];
+        }
+        *value = det(replaced) / denominator;
+    }
+    Some(solution)
+}
+
+fn to_point<T: Coord>(point: Point2D<T>) -> Point<T> {
+    (point.x, point.y)
+}
+
+fn distance_to_segment<T: Coord>(point: Point2D<T>, a: Point2D<T>, b: Point2D<T>) -> T {
+    let line = b - a;
+    let length_squared = line.square_length();
+    if length_squared == T::zero() {
+        return point.distance_to(a);
+    }
+    let t = ((point - a).dot(line) / length_squared).max(T::zero()).min(T::one());
+    point.distance_to(a + line * t)
+}
+
+/// The unsigned angle in degrees between two directions.
+fn angle_between<T: Coord>(a: Vector2D<T>, b: Vector2D<T>) -> T {
+    let angle = a.cross(b).atan2(a.dot(b));
+    angle.to_degrees().abs()
+}
+
+/// Recognizes the simple geometric shape that best fits a path. Returns an error if the
+/// path is too short, or does not closely resemble any shape.
+pub fn recognize_shape<T: Coord>(path: &Path2D<T>) -> Result<ShapeMatch<T>, Error> {
+    if path.points.len() < 2 || path.length() < T::constant(100.0) {
+        return Err(Error::TooShort);
+    }
+    let path = path.resample(NUM_POINTS);
+    let size = path.bounding_rect().size().to_vector().length();
+    let count = T::from_count(path.points.len());
+    let score_for = |total_error: T| T::one() - total_error / count / size;
+
+    let mut candidates = vec![];
+    let chord = path.points[0].distance_to(path.points[path.points.len() - 1]);
+    if chord / path.length() > T::constant(LINE_STRAIGHTNESS) {
+        if let Some((start, end)) = path.fit_line() {
+            let line = (Point2D::from(start), Point2D::from(end));
+            let error = path.points.iter().map(|&p| distance_to_segment(p, line.0, line.1)).sum();
+            candidates.push(ShapeMatch {
+                shape: Shape::Line { start, end },
+                score: score_for(error),
+            });
+        }
+    } else if path.is_closed(T::constant(CLOSED_GAP.into())) {
+        candidates.extend(fit_polygon(&path, &score_for));
+        candidates.extend(fit_round(&path, &score_for));
+    } else {
+        candidates.extend(fit_arrow(&path, size));
+    }
+
+    candidates
+        .into_iter()
+        .filter(|c| c.score >= T::constant(MIN_SCORE))
+        .max_by(|a, b| a.score.total_cmp(&b.score))
+        .ok_or(Error::NoMatch)
+}
+
+/// Fit a triangle or rectangle to the corners of a closed path.
+fn fit_polygon<T: Coord>(path: &Path2D<T>, score_for: &dyn Fn(T) -> T) -> Option<ShapeMatch<T>> {
+    let mut vertices: Vec<Point2D<T>> = path.corners().iter().map(|&i| path.points[i]).collect();
+    // The end of a closed path meets its start.
+    vertices.pop();
+    // A path started partway along an edge has a spurious corner at its start.
+    if vertices.len() > 3 {
+        let count = vertices.len();
+        let incoming = vertices[0] - vertices[count - 1];
+        let outgoing = vertices[1] - vertices[0];
+        if angle_between(incoming, outgoing) < T::constant(COLLINEAR_TOLERANCE) {
+            vertices.remove(0);
+        }
+    }
+
+    let count = vertices.len();
+    let angles: Vec<T> = (0..count)
+        .map(|i| {
+            let incoming = vertices[i] - vertices[(i + count - 1) % count];
+            let outgoing = vertices[(i + 1) % count] - vertices[i];
+            angle_between(incoming, outgoing)
+        })
+        .collect();
+    let right_angle = |a: &T| {
+        (*a - T::constant(90.0)).abs() <= T::constant(RIGHT_ANGLE_TOLERANCE)
+    };
+    let shape = match vertices[..] {
+        [a, b, c] => Shape::Triangle { corners: [to_point(a), to_point(b), to_point(c)] },
+        [a, b, c, d] if angles.iter().all(right_angle) => {
+            Shape::Rectangle { corners: [to_point(a), to_point(b), to_point(c), to_point(d)] }
+        }
+        _ => return None,
+    };
+
+    let error = path.points
+        .iter()
+        .map(|&p| {
+            (0..count)
+                .map(|i| distance_to_segment(p, vertices[i], vertices[(i + 1) % count]))
+                .fold(T::max_value(), T::min)
+        })
+        .sum();
+    Some(ShapeMatch { shape, score: score_for(error) })
+}
+
+/// Fit a circle or ellipse to a closed path.
+fn fit_round<T: Coord>(path: &Path2D<T>, score_for: &dyn Fn(T) -> T) -> Option<ShapeMatch<T>> {
+    let (center, radii, rotation) = path.fit_ellipse()?;
+    if radii.1 / radii.0 >= T::constant(ROUND_RATIO) {
+        let (center, radius) = path.fit_circle()?;
+        let error = path.points
+            .iter()
+            .map(|p| (p.distance_to(Point2D::from(center)) - radius).abs())
+            .sum();
+        return Some(ShapeMatch {
+            shape: Shape::Circle { center, radius },
+            score: score_for(error),
+        });
+    }
+
+    let major = Vector2D::new(Float::cos(rotation), Float::sin(rotation));
+    let minor = Vector2D::new(-major.y, major.x);
+    let error = path.points
+        .iter()
+        .map(|&p| {
+            // The distance to the ellipse along the ray from its center.
+            let offset = p - Point2D::from(center);
+            let (u, v) = (offset.dot(major), offset.dot(minor));
+            let scale = (u / radii.0).hypot(v / radii.1);
+            if scale == T::zero() {
+                return radii.1;
+            }
+            offset.length() * (T::one() - scale.recip()).abs()
+        })
+        .sum();
+    Some(ShapeMatch {
+        shape: Shape::Ellipse { center, radii, rotation },
+        score: score_for(error),
+    })
+}
+
+/// Fit an arrow to an open path: a long straight shaft, followed by a head drawn close to
+/// the end of the shaft that doubles back on it.
+fn fit_arrow<T: Coord>(path: &Path2D<T>, size: T) -> Option<ShapeMatch<T>> {
+    let segments = path.segments();
+    let (shaft, head) = segments.split_first()?;
+    let half = T::constant(0.5);
+    if !shaft.straight || shaft.length < half * path.length() || head.len() < 2 {
+        return None;
+    }
+
+    let tail = path.points[shaft.start];
+    let tip = path.points[shaft.end];
+    let direction = tip - tail;
+    let head_size = half * direction.length();
+    if path.points[shaft.end..].iter().any(|p| p.distance_to(tip) > head_size) {
+        return None;
+    }
+    let doubles_back = head.iter().any(|segment| {
+        let barb = path.points[segment.end] - path.points[segment.start];
+        barb.dot(direction) < T::zero()
+    });
+    if !doubles_back {
+        return None;
+    }
+
+    let shaft_points = &path.points[shaft.start..=shaft.end];
+    let error: T = shaft_points.iter().map(|&p| distance_to_segment(p, tail, tip)).sum();
+    let count = T::from_count(shaft_points.len());
+    Some(ShapeMatch {
+        shape: Shape::Arrow { tail: to_point(tail), head: to_point(tip) },
+        score: T::one() - error / count / size,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn polyline(points: &[(f32, f32)]) -> Path2D {
+        let mut path = Path2D::default();
+        for pair in points.windows(2) {
+            let [(x0, y0), (x1, y1)] = pair else { unreachable!() };
+            for i in 0..20 {
+                let t = i as f32 / 20.0;
+                path.push(x0 + (x1 - x0) * t, y0 + (y1 - y0) * t);
+            }
+        }
+        let &(x, y) = points.last().unwrap();
+        path.push(x, y);
+        path
+    }
+
+    fn ellipse(radii: (f32, f32), start: f32) -> Path2D {
+        let mut path = Path2D::default();
+        for i in 0..=100 {
+            let angle = start + i as f32 / 100.0 * core::f32::consts::TAU;
+            path.push(300.0 + radii.0 * angle.cos(), 200.0 + radii.1 * angle.sin());
+        }
+        path
+    }
+
+    fn recognize(path: &Path2D) -> Shape {
+        recognize_shape(path).unwrap().shape
+    }
+
+    #[test]
+    fn fitted_parameters() {
+        let Shape::Line { start, end } = recognize(&polyline(&[(0., 0.), (300., 100.)])) else {
+            panic!()
+        };
+        assert!(start.0.abs() < 1.0 && (end.1 - 100.0).abs() < 1.0);
+
+        let Shape::Circle { center, radius } = recognize(&ellipse((100., 100.), 1.0)) else {
+            panic!()
+        };
+        assert!((center.0 - 300.0).abs() < 1.0 && (center.1 - 200.0).abs() < 1.0);
+        assert!((radius - 100.0).abs() < 1.0);
+
+        let Shape::Ellipse { radii, rotation, .. } = recognize(&ellipse((200., 80.), 0.0)) else {
+            panic!()
+        };
+        assert!((radii.0 - 200.0).abs() < 10.0 && (radii.1 - 80.0).abs() < 10.0);
+        assert!(rotation.sin().abs() < 0.05);
+
+        assert!(!polyline(&[(0., 0.), (200., 0.), (200., 200.)]).is_closed(CLOSED_GAP));
+    }
+
+    #[test]
+    fn shapes_far_from_the_origin() {
+        // Too far from the origin for single precision to resolve the drawing.
+        let (x, y) = (450_000_000.0, 600_000_000.0);
+        let corners = [(0., 0.), (300., 0.), (300., 200.), (0., 200.), (0., 0.)];
+        let mut path = Path2D::<f64>::default();
+        for pair in corners.windows(2) {
+            let [(x0, y0), (x1, y1)] = pair else { unreachable!() };
+            for i in 0..20 {
+                let t = i as f64 / 20.0;
+                path.push(x + x0 + (x1 - x0) * t, y + y0 + (y1 - y0) * t);
+            }
+        }
+        path.push(x, y);
+        let Shape::Rectangle { corners } = recognize_shape(&path).unwrap().shape else {
+            panic!()
+        };
+        assert!((corners[2].0 - x - 300.0).abs() < 10.0 && (corners[2].1 - y - 200.0).abs() < 10.0);
+    }
+
+    #[test]
+    fn shape_types() {
+        // Started partway along the bottom edge.
+        let square = [(100., 0.), (200., 0.), (200., 200.), (0., 200.), (0., 0.), (100., 0.)];
+        assert_eq!(recognize(&polyline(&square)).name(), "rectangle");
+        let triangle = [(0., 0.), (200., 0.), (100., 170.), (0., 0.)];
+        assert_eq!(recognize(&polyline(&triangle)).name(), "triangle");
+
+        let arrow = [(0., 0.), (300., 0.), (250., 40.), (250., -40.), (300., 0.)];
+        let Shape::Arrow { tail, head } = recognize(&polyline(&arrow)) else { panic!() };
+        assert_eq!(tail, (0., 0.));
+        assert!((head.0 - 300.0).abs() < 10.0 && head.1.abs() < 10.0);
+
+        let zigzag = [(0., 0.), (200., 0.), (0., 200.), (200., 200.)];
+        assert!(matches!(recognize_shape(&polyline(&zigzag)), Err(Error::NoMatch)));
+    }
+}
@@ -0,0 +1,328 @@
+//! Recognition of gestures in an unbounded stream of points, without explicit start
+//! and stop signals.
+//!
+//! A [Segmenter] keeps a sliding window of recent timestamped points. When the stream
+//! pauses, or when the pointer's speed drops after a period of movement, the window is
+//! treated as a potential gesture boundary: the window and several of its suffixes are
+//! matched against the templates, and the best match is reported if it is good enough.
+//!
+//! Gestures drawn one after another without slowing down are separated by match scores.
+//! While the pointer moves, the window is matched at regular intervals; once a match is
+//! good enough, the window keeps growing until its score starts to fall, and the best
+//! match is reported at that point. Points drawn after it start the next gesture.
+
+use crate::{find_matching_template, Path2D, PathCoord, Template};
+use num_traits::Float;
+use alloc::collections::VecDeque;
+
+/// The number of suffixes of the window that are considered at each boundary.
+const SUFFIXES: usize = 5;
+
+/// Parameters controlling how a [Segmenter] detects gesture boundaries.
+#[derive(Debug, Clone, Copy)]
+pub struct SegmenterConfig {
+    /// Seconds without any new points after which the current window is evaluated and
+    /// discarded.
+    pub pause: f32,
+    /// Speed, in units per second, below which a moving pointer is considered to have
+    /// stopped.
+    pub min_speed: f32,
+    /// Seconds of movement over which the current speed is measured.
+    pub speed_window: f32,
+    /// The longest duration, in seconds, that a single gesture may take. Older points
+    /// are discarded from the window.
+    pub max_duration: f32,
+    /// The lowest score at which a match is reported.
+    pub min_score: f32,
+    /// The range of rotation in degrees in which paths are compared against templates.
+    pub angle_range: f32,
+    /// The precision in degrees at which rotations are attempted.
+    pub angle_precision: f32,
+    /// Seconds between matches of the window while the pointer is moving.
+    pub match_interval: f32,
+}
+
+impl Default for SegmenterConfig {
+    fn default() -> Self {
+        SegmenterConfig {
+            pause: 0.3,
+            min_speed: 100.0,
+            speed_window: 0.1,
+            max_duration: 3.0,
+            min_score: 0.85,
+            angle_range: 45.0,
+            angle_precision: 2.0,
+            match_interval: 0.05,
+        }
+    }
+}
+
+/// A gesture found in the stream of points.
+#[derive(Debug, Clone)]
+pub struct Segment {
+    /// The index of the matching template.
+    pub template: usize,
+    /// How closely the points match the template, between 0.0 and 1.0.
+    pub score: f32,
+    /// The points that were recognized as the gesture.
+    pub path: Path2D,
+}
+
+#[derive(Clone, Copy)]
+struct TimedPoint {
+    x: PathCoord,
+    y: PathCoord,
+    time: f32,
+}
+
+/// The best match of the window found while the pointer was moving.
+struct Peak {
+    segment: Segment,
+    /// The time of the last point of the matching window.
+    time: f32,
+}
+
+/// Splits a continuous stream of points into recognized gestures.
+pub struct Segmenter {
+    /// The parameters used to detect gesture boundaries.
+    pub config: SegmenterConfig,
+    window: VecDeque<TimedPoint>,
+    moving: bool,
+    /// The time at which the window was last matched while the pointer was moving.
+    last_match: Option<f32>,
+    peak: Option<Peak>,
+}
+
+impl Segmenter {
+    /// Create a segmenter with an empty window.
+    pub fn new(config: SegmenterConfig) -> Segmenter {
+        Segmenter {
+            config,
+            window: VecDeque::new(),
+            moving: false,
+            last_match: None,
+            peak: None,
+        }
+    }
+
+    /// Discard all points in the current window.
+    pub fn reset(&mut self) {
+        self.window.clear();
+        self.moving = false;
+        self.last_match = None;
+        self.peak = None;
+    }
+
+    /// Add a point observed at `time` seconds. Returns a gesture if this point
+    /// completed one.
+    pub fn push(
+        &mut self,
+        templates: &[Template],
+        x: PathCoord,
+        y: PathCoord,
+        time: f32,
+    ) -> Option<Segment> {
+        // A pause that was not reported through `update` still ends the previous gesture.
+        let mut segment = self.update(templates, time);
+
+        if let Some(last) = self.window.back() {
+            if last.x == x && last.y == y {
+                return segment;
+            }
+        }
+        self.window.push_back(TimedPoint { x, y, time });
+        while self.window.front().is_some_and(|p| time - p.time > self.config.max_duration) {
+            self.window.pop_front();
+        }
+
+        let speed = self.speed();
+        if speed >= self.config.min_speed {
+            self.moving = true;
+            if segment.is_none() {
+                segment = self.track_peak(templates, time);
+            }
+        } else if self.moving {
+            self.moving = false;
+            if let Some(found) = self.best_match(templates) {
+                self.window.clear();
+                self.peak = None;
+                segment = Some(found);
+            }
+        }
+        segment
+    }
+
+    /// Check whether the stream has paused as of `time` seconds. This should be called
+    /// regularly, since no points arrive while the pointer is still. Returns a gesture
+    /// if the pause completed one.
+    pub fn update(&mut self, templates: &[Template], time: f32) -> Option<Segment> {
+        let last = self.window.back()?;
+        if time - last.time < self.config.pause {
+            return None;
+        }
+        let segment = self.best_match(templates);
+        self.reset();
+        segment
+    }
+
+    /// Match the window if `match_interval` has passed since it was last matched. Returns
+    /// the peak match once the window's score falls below it, and removes the points of
+    /// that match from the window.
+    fn track_peak(&mut self, templates: &[Template], time: f32) -> Option<Segment> {
+        if self.last_match.is_some_and(|last| time - last < self.config.match_interval) {
+            return None;
+        }
+        self.last_match = Some(time);
+        let current = self.evaluate(templates);
+        let falling = self.peak.as_ref().is_some_and(|peak| {
+            current.as_ref().is_none_or(|current| current.score < peak.segment.score)
+        });
+        if falling {
+            let peak = self.peak.take()?;
+            self.window.retain(|point| point.time > peak.time);
+            return Some(peak.segment);
+        }
+        if let Some(current) = current {
+            self.peak = Some(Peak { segment: current, time });
+        }
+        None
+    }
+
+    /// Returns the better of the peak match and a match of the whole window.
+    fn best_match(&mut self, templates: &[Template]) -> Option<Segment> {
+        let found = self.evaluate(templates);
+        match (found, self.peak.take()) {
+            (Some(found), Some(peak)) if peak.segment.score > found.score => Some(peak.segment),
+            (found, peak) => found.or(peak.map(|peak| peak.segment)),
+        }
+    }
+
+    /// The average speed of the pointer over the most recent `speed_window` seconds.
+    fn speed(&self) -> f32 {
+        let Some(last) = self.window.back() else { return 0.0 };
+        let mut distance = 0.0;
+        let mut duration = 0.0;
+        let mut points = self.window.iter().rev();
+        let mut previous = points.next().copied();
+        for point in points {
+            let Some(next) = previous else { break };
+            if last.time - point.time > self.config.speed_window {
+                break;
+            }
+            distance += Float::hypot(next.x - point.x, next.y - point.y);
+            duration = last.time - point.time;
+            previous = Some(*point);
+        }
+        if duration <= 0.0 {
+            // Not enough history to measure; assume the pointer is moving.
+            return f32::MAX;
+        }
+        distance / duration
+    }
+
+    /// Match the window and several of its suffixes against the templates. Returns the
+    /// best match above the minimum score.
+    fn evaluate(&self, templates: &[Template]) -> Option<Segment> {
+        let len = self.window.len();
+        let mut best: Option<Segment> = None;
+        for suffix in 0..SUFFIXES {
+            let start = suffix * len / SUFFIXES;
+            let mut path = Path2D::default();
+            for point in self.window.range(start..) {
+                path.push(point.x, point.y);
+            }
+            let Ok((template, score)) = find_matching_template(
+                templates,
+                &path,
+                self.config.angle_range,
+                self.config.angle_precision,
+            ) else {
+                continue
+            };
+            if score < self.config.min_score || best.as_ref().is_some_and(|b| b.score >= score) {
+                continue;
+            }
+            let index = templates
+                .iter()
+                .position(|t| core::ptr::eq(t, template))
+                .unwrap();
+            best = Some(Segment { template: index, score, path });
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn corners(points: &[(f32, f32)], steps: usize) -> Vec<(f32, f32)> {
+        let mut path = vec![];
+        for pair in points.windows(2) {
+            let [(x0, y0), (x1, y1)] = pair else { unreachable!() };
+            for i in 0..steps {
+                let t = i as f32 / steps as f32;
+                path.push((x0 + (x1 - x0) * t, y0 + (y1 - y0) * t));
+            }
+        }
+        path.push(*points.last().unwrap());
+        path
+    }
+
+    fn template(name: &str, points: &[(f32, f32)]) -> Template {
+        let mut path = Path2D::default();
+        for (x, y) in corners(points, 10) {
+            path.push(x, y);
+        }
+        Template::new(name.to_owned(), &path).unwrap()
+    }
+
+    #[test]
+    fn gestures_are_found_after_pauses() {
+        let zigzag = [(0., 0.), (200., 0.), (0., 200.), (200., 200.)];
+        let triangle = [(0., 0.), (200., 0.), (100., 200.), (0., 0.)];
+        let templates = [template("zigzag", &zigzag), template("triangle", &triangle)];
+        let mut segmenter = Segmenter::new(SegmenterConfig::default());
+
+        let mut time = 0.0;
+        let mut found = vec![];
+        for shape in [&zigzag[..], &triangle[..]] {
+            for (x, y) in corners(shape, 30) {
+                time += 0.01;
+                found.extend(segmenter.push(&templates, x + 500., y + 500., time));
+            }
+            // The pointer rests until the pause is detected.
+            time += 0.5;
+            found.extend(segmenter.update(&templates, time));
+        }
+        let names: Vec<_> = found.iter().map(|s| templates[s.template].name.as_str()).collect();
+        assert_eq!(names, ["zigzag", "triangle"]);
+    }
+
+    #[test]
+    fn gestures_are_found_without_pauses() {
+        let zigzag = [(0., 0.), (200., 0.), (0., 200.), (200., 200.)];
+        let triangle = [(0., 0.), (200., 0.), (100., 200.), (0., 0.)];
+        let templates = [template("zigzag", &zigzag), template("triangle", &triangle)];
+        let mut segmenter = Segmenter::new(SegmenterConfig::default());
+
+        // The triangle starts where the zigzag ends, at the same speed.
+        let mut points = corners(&zigzag, 30);
+        points.pop();
+        points.extend(corners(&triangle, 30).into_iter().map(|(x, y)| (x + 200., y + 200.)));
+        let mut time = 0.0;
+        let mut found = vec![];
+        for (x, y) in points {
+            time += 0.01;
+            found.extend(segmenter.push(&templates, x, y, time));
+        }
+        assert_eq!(found.len(), 1);
+        assert_eq!(templates[found[0].template].name, "zigzag");
+        assert!(found[0].path.points().len() <= 95, "{}", found[0].path.points().len());
+
+        time += 0.5;
+        found.extend(segmenter.update(&templates, time));
+        let names: Vec<_> = found.iter().map(|s| templates[s.template].name.as_str()).collect();
+        assert_eq!(names, ["zigzag", "triangle"]);
+    }
+}
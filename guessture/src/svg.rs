@@ -0,0 +1,204 @@
+//! Conversion between paths and SVG, so that gestures can be authored and reviewed in
+//! vector drawing tools.
+//!
+//! Curves and arcs in imported path data are flattened into line segments. Transforms
+//! and other styling in imported documents are ignored; only path geometry is used.
+
+use crate::{escape_xml, Path2D, PathCoord, Template};
+use euclid::default::Point2D;
+use std::fmt::{self, Write};
+use svgtypes::{SimplePathSegment, SimplifyingPathParser};
+
+/// The number of line segments each curve is flattened into.
+const CURVE_SEGMENTS: usize = 16;
+
+#[derive(Debug)]
+pub enum Error {
+    /// The path data could not be parsed.
+    PathData(svgtypes::Error),
+    /// The document was not well-formed XML.
+    Document(roxmltree::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::PathData(err) => write!(f, "invalid path data: {}", err),
+            Error::Document(err) => write!(f, "invalid document: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl Path2D {
+    /// Create a path from the contents of an SVG path element's `d` attribute. Curves and
+    /// arcs are flattened into line segments, and multiple subpaths are joined in order.
+    pub fn from_svg_path_data(d: &str) -> Result<Path2D, Error> {
+        let mut path = Path2D::default();
+        let mut current = Point2D::new(0.0, 0.0);
+        let mut subpath_start = current;
+        for segment in SimplifyingPathParser::from(d) {
+            match segment.map_err(Error::PathData)? {
+                SimplePathSegment::MoveTo { x, y } => {
+                    current = point(x, y);
+                    subpath_start = current;
+                    push_point(&mut path, current);
+                }
+                SimplePathSegment::LineTo { x, y } => {
+                    current = point(x, y);
+                    push_point(&mut path, current);
+                }
+                SimplePathSegment::Quadratic { x1, y1, x, y } => {
+                    let (p1, p2) = (point(x1, y1), point(x, y));
+                    for i in 1..=CURVE_SEGMENTS {
+                        let t = i as PathCoord / CURVE_SEGMENTS as PathCoord;
+                        push_point(&mut path, current.lerp(p1, t).lerp(p1.lerp(p2, t), t));
+                    }
+                    current = p2;
+                }
+                SimplePathSegment::CurveTo { x1, y1, x2, y2, x, y } => {
+                    let (p1, p2, p3) = (point(x1, y1), point(x2, y2), point(x, y));
+                    for i in 1..=CURVE_SEGMENTS {
+                        let t = i as PathCoord / CURVE_SEGMENTS as PathCoord;
+                        let a = current.lerp(p1, t).lerp(p1.lerp(p2, t), t);
+                        let b = p1.lerp(p2, t).lerp(p2.lerp(p3, t), t);
+                        push_point(&mut path, a.lerp(b, t));
+                    }
+                    current = p3;
+                }
+                SimplePathSegment::ClosePath => {
+                    current = subpath_start;
+                    push_point(&mut path, current);
+                }
+            }
+        }
+        Ok(path)
+    }
+
+    /// Returns SVG path data (suitable for a `d` attribute) describing this path as a
+    /// series of straight lines.
+    pub fn to_svg_path_data(&self) -> String {
+        let mut d = String::new();
+        for (i, point) in self.points.iter().enumerate() {
+            let command = if i == 0 { 'M' } else { 'L' };
+            let _ = write!(d, "{}{} {} ", command, point.x, point.y);
+        }
+        d.truncate(d.trim_end().len());
+        d
+    }
+}
+
+fn point(x: f64, y: f64) -> Point2D<PathCoord> {
+    Point2D::new(x as PathCoord, y as PathCoord)
+}
+
+fn push_point(path: &mut Path2D, point: Point2D<PathCoord>) {
+    if path.is_new_point(point.x, point.y) {
+        path.push(point.x, point.y);
+    }
+}
+
+/// Returns a path for every `<path>` element in an SVG document, in document order.
+pub fn paths_from_document(svg: &str) -> Result<Vec<Path2D>, Error> {
+    let document = roxmltree::Document::parse(svg).map_err(Error::Document)?;
+    document
+        .descendants()
+        .filter(|node| node.has_tag_name("path"))
+        .filter_map(|node| node.attribute("d"))
+        .map(Path2D::from_svg_path_data)
+        .collect()
+}
+
+/// Returns a standalone SVG document showing the provided path, with a marker at its
+/// starting point.
+pub fn path_to_svg(path: &Path2D) -> String {
+    document(path, None)
+}
+
+/// Returns a standalone SVG document showing the normalized path of the provided
+/// template, with a marker at its starting point and the template's name as its title.
+pub fn template_to_svg(template: &Template) -> String {
+    document(&template.path, Some(&template.name))
+}
+
+fn document(path: &Path2D, title: Option<&str>) -> String {
+    let bounds = if path.points.is_empty() {
+        euclid::default::Box2D::zero()
+    } else {
+        path.bounding_rect()
+    };
+    let size = bounds.width().max(bounds.height()).max(1.0);
+    let margin = size * 0.05;
+    let stroke_width = size * 0.01;
+
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{} {} {} {}\">",
+        bounds.min.x - margin,
+        bounds.min.y - margin,
+        bounds.width() + 2.0 * margin,
+        bounds.height() + 2.0 * margin,
+    );
+    if let Some(title) = title {
+        let _ = writeln!(svg, "  <title>{}</title>", escape_xml(title));
+    }
+    let _ = writeln!(
+        svg,
+        "  <path d=\"{}\" fill=\"none\" stroke=\"black\" stroke-width=\"{}\" \
+         stroke-linecap=\"round\" stroke-linejoin=\"round\"/>",
+        path.to_svg_path_data(),
+        stroke_width,
+    );
+    if let Some(start) = path.points.first() {
+        let _ = writeln!(
+            svg,
+            "  <circle class=\"start\" cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"green\"/>",
+            start.x,
+            start.y,
+            stroke_width * 3.0,
+        );
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flatten_path_data() {
+        let path = Path2D::from_svg_path_data("M 0 0 L 10 0 l 0 10 H 0 Z").unwrap();
+        assert_eq!(
+            path.points(),
+            vec![(0., 0.), (10., 0.), (10., 10.), (0., 10.), (0., 0.)],
+        );
+
+        let path = Path2D::from_svg_path_data("M 0 0 C 0 10 10 10 10 0").unwrap();
+        assert_eq!(path.points().len(), CURVE_SEGMENTS + 1);
+        assert_eq!(path.points()[CURVE_SEGMENTS / 2], (5., 7.5));
+
+        let path = Path2D::from_svg_path_data("M 10 0 A 10 10 0 0 1 -10 0").unwrap();
+        assert!(path.points().len() > 2);
+        for (x, y) in path.points() {
+            assert!(((x * x + y * y).sqrt() - 10.0).abs() < 0.1);
+        }
+
+        assert!(Path2D::from_svg_path_data("M 0 0 L x").is_err());
+    }
+
+    #[test]
+    fn export_and_reimport() {
+        let mut path = Path2D::default();
+        path.push(1., 2.);
+        path.push(30., 40.);
+        path.push(50., 10.);
+        let svg = path_to_svg(&path);
+        assert!(svg.contains("<circle class=\"start\" cx=\"1\" cy=\"2\""));
+        let paths = paths_from_document(&svg).unwrap();
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0].points(), path.points());
+    }
+}
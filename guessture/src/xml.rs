@@ -0,0 +1,333 @@
+//! Import and export of the XML gesture logs used by the published $1 and $N datasets.
+//!
+//! The University of Washington $1 unistroke corpus stores one gesture per file as a
+//! `<Gesture>` element containing `<Point X=".." Y=".." T=".."/>` children. The Mixed
+//! Multistroke Gestures (MMG) corpus used to evaluate $N uses the same root element, but
+//! groups points into `<Stroke>` elements. Both layouts are accepted by [XmlGesture::parse],
+//! and either can be produced by [XmlGesture::to_xml].
+
+use crate::eval::Dataset;
+use crate::{escape_xml, Path2D, PathCoord, Template};
+use std::fmt::{self, Write};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// A single point of a logged gesture.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct XmlPoint {
+    /// The horizontal position of the point.
+    pub x: PathCoord,
+    /// The vertical position of the point.
+    pub y: PathCoord,
+    /// The time at which the point was recorded, in milliseconds, if known.
+    pub t: Option<u64>,
+}
+
+/// The layout to use when writing a gesture as XML.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Points are direct children of the `<Gesture>` element, as in the $1 corpus.
+    /// Multiple strokes are joined into a single stroke.
+    Unistroke,
+    /// Points are grouped into `<Stroke>` elements, as in the $N MMG corpus.
+    Multistroke,
+}
+
+/// A gesture sample read from, or destined for, a dataset XML file.
+#[derive(Debug, Clone, Default)]
+pub struct XmlGesture {
+    /// The raw name of the sample, such as `arrow01`.
+    pub name: String,
+    /// The identifier of the person that recorded the sample.
+    pub subject: String,
+    /// The articulation speed the sample was recorded at (`slow`, `medium` or `fast`).
+    pub speed: Option<String>,
+    /// The strokes that make up the gesture, in the order they were drawn.
+    pub strokes: Vec<Vec<XmlPoint>>,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    /// The file could not be read or written.
+    Io(io::Error),
+    /// The document was not well-formed XML.
+    Parse(roxmltree::Error),
+    /// The root element was not `<Gesture>`.
+    NotAGesture,
+    /// A required attribute was not present on an element.
+    MissingAttribute(&'static str),
+    /// An attribute that should be numeric could not be parsed.
+    InvalidNumber(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "{}", err),
+            Error::Parse(err) => write!(f, "{}", err),
+            Error::NotAGesture => write!(f, "root element is not <Gesture>"),
+            Error::MissingAttribute(name) => write!(f, "missing attribute {}", name),
+            Error::InvalidNumber(value) => write!(f, "invalid number {:?}", value),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
+impl From<roxmltree::Error> for Error {
+    fn from(err: roxmltree::Error) -> Error {
+        Error::Parse(err)
+    }
+}
+
+impl XmlGesture {
+    /// Create a single-stroke gesture from a recorded path. No timestamps are recorded.
+    pub fn from_path(name: String, subject: String, path: &Path2D) -> XmlGesture {
+        XmlGesture {
+            name,
+            subject,
+            speed: None,
+            strokes: vec![path
+                .points()
+                .into_iter()
+                .map(|(x, y)| XmlPoint { x, y, t: None })
+                .collect()],
+        }
+    }
+
+    /// Parse a gesture from the contents of a $1 or $N dataset XML file.
+    pub fn parse(xml: &str) -> Result<XmlGesture, Error> {
+        let document = roxmltree::Document::parse(xml)?;
+        let root = document.root_element();
+        if !root.has_tag_name("Gesture") {
+            return Err(Error::NotAGesture);
+        }
+
+        let mut strokes = vec![];
+        let mut loose_points = vec![];
+        for child in root.children().filter(|n| n.is_element()) {
+            if child.has_tag_name("Stroke") {
+                let points = child
+                    .children()
+                    .filter(|n| n.has_tag_name("Point"))
+                    .map(parse_point)
+                    .collect::<Result<Vec<_>, _>>()?;
+                strokes.push(points);
+            } else if child.has_tag_name("Point") {
+                loose_points.push(parse_point(child)?);
+            }
+        }
+        if !loose_points.is_empty() {
+            strokes.insert(0, loose_points);
+        }
+
+        Ok(XmlGesture {
+            name: root.attribute("Name").ok_or(Error::MissingAttribute("Name"))?.to_owned(),
+            subject: root.attribute("Subject").unwrap_or_default().to_owned(),
+            speed: root.attribute("Speed").map(str::to_owned),
+            strokes,
+        })
+    }
+
+    /// Read and parse a gesture from a $1 or $N dataset XML file.
+    pub fn read(path: &Path) -> Result<XmlGesture, Error> {
+        XmlGesture::parse(&fs::read_to_string(path)?)
+    }
+
+    /// The gesture class of this sample: its name without the trailing sample number,
+    /// so that `arrow01` and `arrowhead~03` become `arrow` and `arrowhead`.
+    pub fn label(&self) -> &str {
+        self.name
+            .trim_end_matches(|c: char| c.is_ascii_digit())
+            .trim_end_matches(['~', '-', '_'])
+    }
+
+    /// Returns all strokes joined in drawing order as a single path.
+    pub fn path(&self) -> Path2D {
+        let mut path = Path2D::default();
+        for point in self.strokes.iter().flatten() {
+            if path.is_new_point(point.x, point.y) {
+                path.push(point.x, point.y);
+            }
+        }
+        path
+    }
+
+    /// Create a normalized template from this gesture, named after its [label](Self::label).
+    pub fn to_template(&self) -> Result<Template, crate::TemplateError> {
+        Template::new(self.label().to_owned(), &self.path())
+    }
+
+    /// Serialize this gesture using the provided layout.
+    pub fn to_xml(&self, format: Format) -> String {
+        let points = self.strokes.iter().flatten();
+        let num_points = points.clone().count();
+        let times: Vec<u64> = points.filter_map(|p| p.t).collect();
+
+        let mut xml = "<?xml version=\"1.0\" encoding=\"utf-8\" standalone=\"yes\"?>\n".to_owned();
+        let _ = write!(
+            xml,
+            "<Gesture Name=\"{}\" Subject=\"{}\"",
+            escape_xml(&self.name),
+            escape_xml(&self.subject),
+        );
+        if let Some(ref speed) = self.speed {
+            let _ = write!(xml, " Speed=\"{}\"", escape_xml(speed));
+        }
+        if format == Format::Multistroke {
+            let _ = write!(xml, " NumStrokes=\"{}\"", self.strokes.len());
+        }
+        let _ = write!(xml, " NumPts=\"{}\"", num_points);
+        if let (Some(first), Some(last)) = (times.iter().min(), times.iter().max()) {
+            // The misspelling matches the original dataset files.
+            let _ = write!(xml, " Millseconds=\"{}\"", last - first);
+        }
+        xml.push_str(">\n");
+
+        match format {
+            Format::Unistroke => {
+                for point in self.strokes.iter().flatten() {
+                    write_point(&mut xml, point, "  ");
+                }
+            }
+            Format::Multistroke => {
+                for (index, stroke) in self.strokes.iter().enumerate() {
+                    let _ = writeln!(
+                        xml,
+                        "  <Stroke index=\"{}\" NumPts=\"{}\">",
+                        index + 1,
+                        stroke.len(),
+                    );
+                    for point in stroke {
+                        write_point(&mut xml, point, "    ");
+                    }
+                    xml.push_str("  </Stroke>\n");
+                }
+            }
+        }
+        xml.push_str("</Gesture>\n");
+        xml
+    }
+}
+
+fn parse_point(node: roxmltree::Node) -> Result<XmlPoint, Error> {
+    let number = |name: &'static str| -> Result<PathCoord, Error> {
+        let value = node.attribute(name).ok_or(Error::MissingAttribute(name))?;
+        value.trim().parse().map_err(|_| Error::InvalidNumber(value.to_owned()))
+    };
+    let t = match node.attribute("T") {
+        Some(value) => Some(value.trim().parse().map_err(|_| Error::InvalidNumber(value.to_owned()))?),
+        None => None,
+    };
+    Ok(XmlPoint {
+        x: number("X")?,
+        y: number("Y")?,
+        t,
+    })
+}
+
+fn write_point(xml: &mut String, point: &XmlPoint, indent: &str) {
+    let _ = write!(xml, "{}<Point X=\"{}\" Y=\"{}\"", indent, point.x, point.y);
+    if let Some(t) = point.t {
+        let _ = write!(xml, " T=\"{}\"", t);
+    }
+    xml.push_str(" />\n");
+}
+
+/// Recursively read every `.xml` file in a directory, in sorted path order.
+pub fn read_dir(dir: &Path) -> Result<Vec<XmlGesture>, Error> {
+    let mut paths = vec![];
+    collect_xml_files(dir, &mut paths)?;
+    paths.sort();
+    paths.iter().map(|path| XmlGesture::read(path)).collect()
+}
+
+fn collect_xml_files(dir: &Path, paths: &mut Vec<std::path::PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_xml_files(&path, paths)?;
+        } else if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("xml")) {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Convert a collection of gestures into a labeled dataset, using each gesture's
+/// [label](XmlGesture::label) and subject.
+pub fn to_dataset<'a>(gestures: impl IntoIterator<Item = &'a XmlGesture>) -> Dataset {
+    let mut dataset = Dataset::default();
+    for gesture in gestures {
+        dataset.push(gesture.label(), gesture.subject.clone(), gesture.path());
+    }
+    dataset
+}
+
+/// Convert a collection of gestures into normalized templates. Gestures without any
+/// points are skipped.
+pub fn to_templates<'a>(gestures: impl IntoIterator<Item = &'a XmlGesture>) -> Vec<Template> {
+    gestures
+        .into_iter()
+        .filter_map(|gesture| gesture.to_template().ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UNISTROKE: &str = r#"<?xml version="1.0" encoding="utf-8" standalone="yes"?>
+<Gesture Name="arrow01" Subject="1" Speed="fast" Number="1" NumPts="3" Millseconds="20" AppName="Gestures">
+  <Point X="10" Y="20" T="1000" />
+  <Point X="15.5" Y="25" T="1010" />
+  <Point X="30" Y="22" T="1020" />
+</Gesture>"#;
+
+    const MULTISTROKE: &str = r#"<Gesture Name="arrowhead~03" Subject="10" Speed="medium" NumPts="4">
+  <Stroke index="1" Finger="index">
+    <Point X="0" Y="0" T="5" Pressure="0" />
+    <Point X="10" Y="10" T="6" Pressure="0" />
+  </Stroke>
+  <Stroke index="2">
+    <Point X="0" Y="20" T="9" Pressure="0" />
+    <Point X="10" Y="10" T="12" Pressure="0" />
+  </Stroke>
+</Gesture>"#;
+
+    #[test]
+    fn parse_and_round_trip() {
+        let gesture = XmlGesture::parse(UNISTROKE).unwrap();
+        assert_eq!((gesture.label(), gesture.subject.as_str()), ("arrow", "1"));
+        assert_eq!(gesture.path().points(), vec![(10., 20.), (15.5, 25.), (30., 22.)]);
+        let reparsed = XmlGesture::parse(&gesture.to_xml(Format::Unistroke)).unwrap();
+        assert_eq!(reparsed.strokes, gesture.strokes);
+        assert_eq!(reparsed.speed.as_deref(), Some("fast"));
+
+        let gesture = XmlGesture::parse(MULTISTROKE).unwrap();
+        assert_eq!(gesture.label(), "arrowhead");
+        assert_eq!(gesture.strokes.len(), 2);
+        assert_eq!(gesture.path().points().len(), 4);
+        let reparsed = XmlGesture::parse(&gesture.to_xml(Format::Multistroke)).unwrap();
+        assert_eq!(reparsed.strokes, gesture.strokes);
+        let joined = XmlGesture::parse(&gesture.to_xml(Format::Unistroke)).unwrap();
+        assert_eq!(joined.strokes.len(), 1);
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(matches!(XmlGesture::parse("<Gesture"), Err(Error::Parse(_))));
+        assert!(matches!(XmlGesture::parse("<Other Name=\"a\"/>"), Err(Error::NotAGesture)));
+        assert!(matches!(
+            XmlGesture::parse("<Gesture Name=\"a\"><Point X=\"1\" Y=\"z\"/></Gesture>"),
+            Err(Error::InvalidNumber(_)),
+        ));
+    }
+}
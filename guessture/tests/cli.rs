@@ -0,0 +1,109 @@
+//! Runs the `guessture` command-line tool against small generated sample directories.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// A scratch directory that is removed when the test finishes.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> TempDir {
+        let name = format!("guessture-cli-{}-{}", name, std::process::id());
+        let dir = std::env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        TempDir(dir)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn guessture(args: &[&Path]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_guessture")).args(args).output().unwrap()
+}
+
+fn write_points(file: &Path, points: impl Iterator<Item = (f32, f32)>) {
+    let points: Vec<_> = points.map(|(x, y)| format!("[{},{}]", x, y)).collect();
+    fs::create_dir_all(file.parent().unwrap()).unwrap();
+    fs::write(file, format!("[{}]", points.join(","))).unwrap();
+}
+
+/// Writes `count` slightly different circles and zigzags below `dir`.
+fn write_samples(dir: &Path, count: usize) {
+    for n in 0..count {
+        let wobble = n as f32 * 3.0;
+        let circle = (0..=40).map(|i| {
+            let t = i as f32 / 40.0 * std::f32::consts::TAU;
+            let r = 150.0 + wobble * (t * 3.0).sin();
+            (r * t.cos(), r * t.sin())
+        });
+        write_points(&dir.join(format!("circle/{}.json", n)), circle);
+        let zigzag = (0..=40).map(|i| {
+            let t = i as f32 / 20.0;
+            let y = if t <= 1.0 { 200.0 * t } else { 200.0 * (2.0 - t) };
+            (100.0 * t, y + wobble * (t * 5.0).sin())
+        });
+        write_points(&dir.join(format!("zigzag/{}.json", n)), zigzag);
+    }
+}
+
+#[test]
+fn build_and_evaluate() {
+    let dir = TempDir::new("build");
+    let (train, test) = (dir.0.join("train"), dir.0.join("test"));
+    write_samples(&train, 2);
+    write_samples(&test, 3);
+    let templates = dir.0.join("shapes.gestures");
+
+    let output = guessture(&["build".as_ref(), &train, "-o".as_ref(), &templates]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let output = guessture(&["evaluate".as_ref(), &templates, &test]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let report = String::from_utf8(output.stdout).unwrap();
+    assert!(report.contains("samples:  6\n"), "{}", report);
+    assert!(report.contains("accuracy: 1.000\n"), "{}", report);
+}
+
+#[test]
+fn build_rejects_straight_samples() {
+    let dir = TempDir::new("straight");
+    let samples = dir.0.join("samples");
+    write_samples(&samples, 1);
+    let line = samples.join("line/0.json");
+    write_points(&line, (0..=20).map(|i| (i as f32 * 10.0, i as f32 * 5.0)));
+    let templates = dir.0.join("shapes.gestures");
+
+    let output = guessture(&["build".as_ref(), &samples, "-o".as_ref(), &templates]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains(&line.display().to_string()), "{}", stderr);
+    assert!(stderr.contains("\"line\""), "{}", stderr);
+    assert!(!templates.exists());
+}
+
+#[test]
+fn convert_rejects_labels_outside_output() {
+    let dir = TempDir::new("convert");
+    let points: String = (0..20)
+        .map(|i| format!("<Point X=\"{}\" Y=\"{}\" />", i * 10, (i % 5) * 20))
+        .collect();
+    let output = dir.0.join("out");
+    for (index, name) in ["../escape1", "/escape2", "nested/escape3"].iter().enumerate() {
+        let input = dir.0.join(format!("{}.xml", index));
+        fs::write(&input, format!("<Gesture Name=\"{}\">{}</Gesture>", name, points)).unwrap();
+
+        let result = guessture(&["convert".as_ref(), &input, &output]);
+        assert!(!result.status.success());
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        assert!(stderr.contains("cannot be used as a directory name"), "{}", stderr);
+    }
+    assert!(!dir.0.join("escape").exists());
+    assert!(!Path::new("/escape").exists());
+    assert!(!output.join("nested").exists());
+}
@@ -0,0 +1,185 @@
+#ifndef GUESSTURE_H
+#define GUESSTURE_H
+
+/* Generated by cbindgen from guessture_ffi/src/lib.rs. Do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * The result of a call into the library.
+ */
+typedef enum GuesstureStatus {
+  /**
+   * The call succeeded.
+   */
+  GUESSTURE_STATUS_OK = 0,
+  /**
+   * A required pointer argument was null.
+   */
+  GUESSTURE_STATUS_NULL_POINTER = 1,
+  /**
+   * A string argument was not valid UTF-8.
+   */
+  GUESSTURE_STATUS_INVALID_STRING = 2,
+  /**
+   * The gesture data could not be parsed, or failed validation.
+   */
+  GUESSTURE_STATUS_INVALID_JSON = 3,
+  /**
+   * The path was empty.
+   */
+  GUESSTURE_STATUS_EMPTY_PATH = 4,
+  /**
+   * The path was too short to be matched.
+   */
+  GUESSTURE_STATUS_TOO_SHORT = 5,
+  /**
+   * No template matched the path.
+   */
+  GUESSTURE_STATUS_NO_MATCH = 6,
+} GuesstureStatus;
+
+/**
+ * A path of points recorded from user input.
+ */
+typedef struct GuessturePath GuessturePath;
+
+/**
+ * A set of named templates that paths are matched against.
+ */
+typedef struct GuesstureTemplates GuesstureTemplates;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Returns a static, NUL-terminated description of `status`.
+ */
+const char *guessture_status_message(GuesstureStatus status);
+
+/**
+ * Create an empty path. The path must be freed with `guessture_path_free`.
+ */
+GuessturePath *guessture_path_new(void);
+
+/**
+ * Append a point to the end of a path.
+ *
+ * # Safety
+ *
+ * `path` must be null or a live path returned by `guessture_path_new`.
+ */
+GuesstureStatus guessture_path_push(GuessturePath *path, float x, float y);
+
+/**
+ * Remove every point from a path, so that it can be reused for the next gesture.
+ *
+ * # Safety
+ *
+ * `path` must be null or a live path returned by `guessture_path_new`.
+ */
+GuesstureStatus guessture_path_clear(GuessturePath *path);
+
+/**
+ * Returns the number of points in a path, or 0 if `path` is null.
+ *
+ * # Safety
+ *
+ * `path` must be null or a live path returned by `guessture_path_new`.
+ */
+uintptr_t guessture_path_len(const GuessturePath *path);
+
+/**
+ * Free a path. Passing null does nothing.
+ *
+ * # Safety
+ *
+ * `path` must be null or a live path returned by `guessture_path_new`, and must not be
+ * used afterwards.
+ */
+void guessture_path_free(GuessturePath *path);
+
+/**
+ * Create an empty template set. The set must be freed with `guessture_templates_free`.
+ */
+GuesstureTemplates *guessture_templates_new(void);
+
+/**
+ * Parse the contents of a `.gestures` file into a new template set, stored in `*out` on
+ * success. The file is validated and migrated to the current schema version.
+ *
+ * # Safety
+ *
+ * `json` must be null or point to a NUL-terminated string, and `out` must be null or
+ * valid for writes.
+ */
+GuesstureStatus guessture_templates_load_json(const char *json, GuesstureTemplates **out);
+
+/**
+ * Create a template named `name` from the points recorded in `path`, and add it to the
+ * end of a template set.
+ *
+ * # Safety
+ *
+ * `templates` and `path` must be null or live handles returned by this library, and
+ * `name` must be null or point to a NUL-terminated string.
+ */
+GuesstureStatus guessture_templates_add(GuesstureTemplates *templates,
+                                        const char *name,
+                                        const GuessturePath *path);
+
+/**
+ * Returns the number of templates in a set, or 0 if `templates` is null.
+ *
+ * # Safety
+ *
+ * `templates` must be null or a live template set returned by this library.
+ */
+uintptr_t guessture_templates_len(const GuesstureTemplates *templates);
+
+/**
+ * Returns the name of the template at `index`, or null if `templates` is null or `index`
+ * is out of range. The name remains valid until the template set is freed.
+ *
+ * # Safety
+ *
+ * `templates` must be null or a live template set returned by this library.
+ */
+const char *guessture_templates_name(const GuesstureTemplates *templates, uintptr_t index);
+
+/**
+ * Free a template set. Passing null does nothing.
+ *
+ * # Safety
+ *
+ * `templates` must be null or a live template set returned by this library, and must not
+ * be used afterwards.
+ */
+void guessture_templates_free(GuesstureTemplates *templates);
+
+/**
+ * Find the template that most closely matches `path`. On success, the index of the
+ * template is stored in `*out_index` and its score between 0.0 and 1.0 in `*out_score`.
+ * `angle_range` and `angle_precision` are in degrees; 45.0 and 2.0 are good defaults.
+ *
+ * # Safety
+ *
+ * `templates` and `path` must be null or live handles returned by this library, and
+ * `out_index` and `out_score` must be null or valid for writes.
+ */
+GuesstureStatus guessture_find_matching_template(const GuesstureTemplates *templates,
+                                                 const GuessturePath *path,
+                                                 float angle_range,
+                                                 float angle_precision,
+                                                 uintptr_t *out_index,
+                                                 float *out_score);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* GUESSTURE_H */
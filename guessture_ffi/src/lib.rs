@@ -0,0 +1,378 @@
+//! C bindings for `guessture`. Paths and template sets are exposed as opaque handles that
+//! are created and freed through this API; every fallible function returns a
+//! [GuesstureStatus]. The matching header is `include/guessture.h`.
+
+use guessture::format::GestureFile;
+use guessture::{find_matching_template, Error, Path2D, Template, TemplateError};
+use std::ffi::{c_char, CStr, CString};
+use std::ptr;
+
+/// The result of a call into the library.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GuesstureStatus {
+    /// The call succeeded.
+    Ok = 0,
+    /// A required pointer argument was null.
+    NullPointer = 1,
+    /// A string argument was not valid UTF-8.
+    InvalidString = 2,
+    /// The gesture data could not be parsed, or failed validation.
+    InvalidJson = 3,
+    /// The path was empty.
+    EmptyPath = 4,
+    /// The path was too short to be matched.
+    TooShort = 5,
+    /// No template matched the path.
+    NoMatch = 6,
+}
+
+impl From<Error> for GuesstureStatus {
+    fn from(error: Error) -> GuesstureStatus {
+        match error {
+            Error::TooShort => GuesstureStatus::TooShort,
+            Error::NoMatch => GuesstureStatus::NoMatch,
+        }
+    }
+}
+
+impl From<TemplateError> for GuesstureStatus {
+    fn from(error: TemplateError) -> GuesstureStatus {
+        match error {
+            TemplateError::PathEmpty => GuesstureStatus::EmptyPath,
+        }
+    }
+}
+
+/// A path of points recorded from user input.
+pub struct GuessturePath(Path2D);
+
+/// A set of named templates that paths are matched against.
+#[derive(Default)]
+pub struct GuesstureTemplates {
+    templates: Vec<Template>,
+    /// NUL-terminated copies of the template names, so that they can be handed out
+    /// without allocating.
+    names: Vec<CString>,
+}
+
+impl GuesstureTemplates {
+    fn push(&mut self, template: Template) {
+        // Names that come from C can't contain NUL bytes, but names from JSON can.
+        let name = template.name.replace('\0', "");
+        self.names.push(CString::new(name).unwrap());
+        self.templates.push(template);
+    }
+}
+
+/// Read a NUL-terminated UTF-8 string.
+///
+/// # Safety
+///
+/// `string` must be null or point to a NUL-terminated string.
+unsafe fn read_str<'a>(string: *const c_char) -> Result<&'a str, GuesstureStatus> {
+    if string.is_null() {
+        return Err(GuesstureStatus::NullPointer);
+    }
+    CStr::from_ptr(string).to_str().map_err(|_| GuesstureStatus::InvalidString)
+}
+
+/// Returns a static, NUL-terminated description of `status`.
+#[no_mangle]
+pub extern "C" fn guessture_status_message(status: GuesstureStatus) -> *const c_char {
+    let message = match status {
+        GuesstureStatus::Ok => c"success",
+        GuesstureStatus::NullPointer => c"a required pointer was null",
+        GuesstureStatus::InvalidString => c"a string was not valid UTF-8",
+        GuesstureStatus::InvalidJson => c"the gesture data could not be parsed or was invalid",
+        GuesstureStatus::EmptyPath => c"the path was empty",
+        GuesstureStatus::TooShort => c"the path was too short to match",
+        GuesstureStatus::NoMatch => c"no template matched the path",
+    };
+    message.as_ptr()
+}
+
+/// Create an empty path. The path must be freed with `guessture_path_free`.
+#[no_mangle]
+pub extern "C" fn guessture_path_new() -> *mut GuessturePath {
+    Box::into_raw(Box::new(GuessturePath(Path2D::default())))
+}
+
+/// Append a point to the end of a path.
+///
+/// # Safety
+///
+/// `path` must be null or a live path returned by `guessture_path_new`.
+#[no_mangle]
+pub unsafe extern "C" fn guessture_path_push(
+    path: *mut GuessturePath,
+    x: f32,
+    y: f32,
+) -> GuesstureStatus {
+    let Some(path) = path.as_mut() else { return GuesstureStatus::NullPointer };
+    path.0.push(x, y);
+    GuesstureStatus::Ok
+}
+
+/// Remove every point from a path, so that it can be reused for the next gesture.
+///
+/// # Safety
+///
+/// `path` must be null or a live path returned by `guessture_path_new`.
+#[no_mangle]
+pub unsafe extern "C" fn guessture_path_clear(path: *mut GuessturePath) -> GuesstureStatus {
+    let Some(path) = path.as_mut() else { return GuesstureStatus::NullPointer };
+    path.0 = Path2D::default();
+    GuesstureStatus::Ok
+}
+
+/// Returns the number of points in a path, or 0 if `path` is null.
+///
+/// # Safety
+///
+/// `path` must be null or a live path returned by `guessture_path_new`.
+#[no_mangle]
+pub unsafe extern "C" fn guessture_path_len(path: *const GuessturePath) -> usize {
+    path.as_ref().map_or(0, |path| path.0.points().len())
+}
+
+/// Free a path. Passing null does nothing.
+///
+/// # Safety
+///
+/// `path` must be null or a live path returned by `guessture_path_new`, and must not be
+/// used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn guessture_path_free(path: *mut GuessturePath) {
+    if !path.is_null() {
+        drop(Box::from_raw(path));
+    }
+}
+
+/// Create an empty template set. The set must be freed with `guessture_templates_free`.
+#[no_mangle]
+pub extern "C" fn guessture_templates_new() -> *mut GuesstureTemplates {
+    Box::into_raw(Box::default())
+}
+
+/// Parse the contents of a `.gestures` file into a new template set, stored in `*out` on
+/// success. The file is validated and migrated to the current schema version.
+///
+/// # Safety
+///
+/// `json` must be null or point to a NUL-terminated string, and `out` must be null or
+/// valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn guessture_templates_load_json(
+    json: *const c_char,
+    out: *mut *mut GuesstureTemplates,
+) -> GuesstureStatus {
+    if out.is_null() {
+        return GuesstureStatus::NullPointer;
+    }
+    let json = match read_str(json) {
+        Ok(json) => json,
+        Err(status) => return status,
+    };
+    let file = GestureFile::from_json(json).and_then(|file| file.to_templates());
+    let Ok(loaded) = file else { return GuesstureStatus::InvalidJson };
+    let mut templates = GuesstureTemplates::default();
+    for template in loaded {
+        templates.push(template);
+    }
+    *out = Box::into_raw(Box::new(templates));
+    GuesstureStatus::Ok
+}
+
+/// Create a template named `name` from the points recorded in `path`, and add it to the
+/// end of a template set.
+///
+/// # Safety
+///
+/// `templates` and `path` must be null or live handles returned by this library, and
+/// `name` must be null or point to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn guessture_templates_add(
+    templates: *mut GuesstureTemplates,
+    name: *const c_char,
+    path: *const GuessturePath,
+) -> GuesstureStatus {
+    let (Some(templates), Some(path)) = (templates.as_mut(), path.as_ref()) else {
+        return GuesstureStatus::NullPointer;
+    };
+    let name = match read_str(name) {
+        Ok(name) => name,
+        Err(status) => return status,
+    };
+    match Template::new(name.to_owned(), &path.0) {
+        Ok(template) => {
+            templates.push(template);
+            GuesstureStatus::Ok
+        }
+        Err(error) => error.into(),
+    }
+}
+
+/// Returns the number of templates in a set, or 0 if `templates` is null.
+///
+/// # Safety
+///
+/// `templates` must be null or a live template set returned by this library.
+#[no_mangle]
+pub unsafe extern "C" fn guessture_templates_len(templates: *const GuesstureTemplates) -> usize {
+    templates.as_ref().map_or(0, |templates| templates.templates.len())
+}
+
+/// Returns the name of the template at `index`, or null if `templates` is null or `index`
+/// is out of range. The name remains valid until the template set is freed.
+///
+/// # Safety
+///
+/// `templates` must be null or a live template set returned by this library.
+#[no_mangle]
+pub unsafe extern "C" fn guessture_templates_name(
+    templates: *const GuesstureTemplates,
+    index: usize,
+) -> *const c_char {
+    templates
+        .as_ref()
+        .and_then(|templates| templates.names.get(index))
+        .map_or(ptr::null(), |name| name.as_ptr())
+}
+
+/// Free a template set. Passing null does nothing.
+///
+/// # Safety
+///
+/// `templates` must be null or a live template set returned by this library, and must not
+/// be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn guessture_templates_free(templates: *mut GuesstureTemplates) {
+    if !templates.is_null() {
+        drop(Box::from_raw(templates));
+    }
+}
+
+/// Find the template that most closely matches `path`. On success, the index of the
+/// template is stored in `*out_index` and its score between 0.0 and 1.0 in `*out_score`.
+/// `angle_range` and `angle_precision` are in degrees; 45.0 and 2.0 are good defaults.
+///
+/// # Safety
+///
+/// `templates` and `path` must be null or live handles returned by this library, and
+/// `out_index` and `out_score` must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn guessture_find_matching_template(
+    templates: *const GuesstureTemplates,
+    path: *const GuessturePath,
+    angle_range: f32,
+    angle_precision: f32,
+    out_index: *mut usize,
+    out_score: *mut f32,
+) -> GuesstureStatus {
+    let (Some(templates), Some(path)) = (templates.as_ref(), path.as_ref()) else {
+        return GuesstureStatus::NullPointer;
+    };
+    if out_index.is_null() || out_score.is_null() {
+        return GuesstureStatus::NullPointer;
+    }
+    let templates = &templates.templates;
+    match find_matching_template(templates, &path.0, angle_range, angle_precision) {
+        Ok((template, score)) => {
+            *out_index = templates.iter().position(|t| ptr::eq(t, template)).unwrap();
+            *out_score = score;
+            GuesstureStatus::Ok
+        }
+        Err(error) => error.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    unsafe fn path(points: &[(f32, f32)]) -> *mut GuessturePath {
+        let path = guessture_path_new();
+        for &(x, y) in points {
+            assert_eq!(guessture_path_push(path, x, y), GuesstureStatus::Ok);
+        }
+        path
+    }
+
+    #[test]
+    fn match_through_handles() {
+        unsafe {
+            let templates = guessture_templates_new();
+            let line = path(&[(0., 0.), (50., 0.), (100., 0.)]);
+            let vee = path(&[(0., 0.), (50., 100.), (100., 0.)]);
+            for (name, path) in [(c"line", line), (c"vee", vee)] {
+                let status = guessture_templates_add(templates, name.as_ptr(), path);
+                assert_eq!(status, GuesstureStatus::Ok);
+            }
+            assert_eq!(guessture_templates_len(templates), 2);
+            assert_eq!(CStr::from_ptr(guessture_templates_name(templates, 1)), c"vee");
+            assert!(guessture_templates_name(templates, 2).is_null());
+
+            let drawn = path(&[(10., 10.), (35., 62.), (60., 12.)]);
+            let (mut index, mut score) = (usize::MAX, 0.0);
+            let status = guessture_find_matching_template(
+                templates, drawn, 45.0, 2.0, &mut index, &mut score,
+            );
+            assert_eq!(status, GuesstureStatus::Ok);
+            assert_eq!(index, 1);
+            assert!(score > 0.8);
+
+            let empty = guessture_path_new();
+            assert_eq!(
+                guessture_templates_add(templates, c"empty".as_ptr(), empty),
+                GuesstureStatus::EmptyPath,
+            );
+            assert_eq!(
+                guessture_find_matching_template(
+                    templates, empty, 45.0, 2.0, &mut index, &mut score,
+                ),
+                GuesstureStatus::TooShort,
+            );
+
+            for path in [line, vee, drawn, empty] {
+                guessture_path_free(path);
+            }
+            guessture_templates_free(templates);
+        }
+    }
+
+    #[test]
+    fn load_json_and_report_errors() {
+        unsafe {
+            let mut templates = ptr::null_mut();
+            let points = vec!["[0.0,0.0]"; 64].join(",");
+            let json = format!("{{\"templates\":[{{\"name\":\"dot\",\"path\":[{}]}}]}}", points);
+            let json = CString::new(json).unwrap();
+            let status = guessture_templates_load_json(json.as_ptr(), &mut templates);
+            assert_eq!(status, GuesstureStatus::Ok);
+            assert_eq!(guessture_templates_len(templates), 1);
+            assert_eq!(CStr::from_ptr(guessture_templates_name(templates, 0)), c"dot");
+            guessture_templates_free(templates);
+
+            // Templates with the wrong number of points fail validation.
+            let mut templates = ptr::null_mut();
+            let json = c"{\"templates\":[{\"name\":\"dot\",\"path\":[[0.0,0.0]]}]}";
+            let status = guessture_templates_load_json(json.as_ptr(), &mut templates);
+            assert_eq!(status, GuesstureStatus::InvalidJson);
+
+            let mut templates = ptr::null_mut();
+            assert_eq!(
+                guessture_templates_load_json(c"{".as_ptr(), &mut templates),
+                GuesstureStatus::InvalidJson,
+            );
+            assert!(templates.is_null());
+            assert_eq!(
+                guessture_templates_load_json(ptr::null(), &mut templates),
+                GuesstureStatus::NullPointer,
+            );
+            assert_eq!(guessture_path_push(ptr::null_mut(), 0., 0.), GuesstureStatus::NullPointer);
+            let message = CStr::from_ptr(guessture_status_message(GuesstureStatus::NoMatch));
+            assert_eq!(message, c"no template matched the path");
+        }
+    }
+}
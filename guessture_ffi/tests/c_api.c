@@ -0,0 +1,88 @@
+/* Exercises the C API through the generated header. Built and run by tests/c_api.rs. */
+
+#include <math.h>
+#include <stdio.h>
+#include <string.h>
+
+#include "guessture.h"
+
+#define CHECK(condition)                                                   \
+  do {                                                                     \
+    if (!(condition)) {                                                    \
+      fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__,     \
+              #condition);                                                 \
+      return 1;                                                            \
+    }                                                                      \
+  } while (0)
+
+static GuessturePath *circle(float cx, float cy, float radius) {
+  GuessturePath *path = guessture_path_new();
+  for (int i = 0; i <= 32; i++) {
+    float angle = (float)i / 32.0f * 6.2831853f;
+    guessture_path_push(path, cx + radius * cosf(angle), cy + radius * sinf(angle));
+  }
+  return path;
+}
+
+static GuessturePath *zigzag(float x, float y, float size) {
+  GuessturePath *path = guessture_path_new();
+  for (int i = 0; i <= 4; i++) {
+    guessture_path_push(path, x + size * i, y + (i % 2 ? size : 0.0f));
+  }
+  return path;
+}
+
+int main(void) {
+  GuesstureTemplates *templates = guessture_templates_new();
+  GuessturePath *path = circle(0.0f, 0.0f, 100.0f);
+  CHECK(guessture_templates_add(templates, "circle", path) == GUESSTURE_STATUS_OK);
+  guessture_path_free(path);
+  path = zigzag(0.0f, 0.0f, 50.0f);
+  CHECK(guessture_templates_add(templates, "zigzag", path) == GUESSTURE_STATUS_OK);
+  guessture_path_free(path);
+  CHECK(guessture_templates_len(templates) == 2);
+
+  uintptr_t index = 0;
+  float score = 0.0f;
+  path = zigzag(300.0f, -20.0f, 30.0f);
+  CHECK(guessture_path_len(path) == 5);
+  CHECK(guessture_find_matching_template(templates, path, 45.0f, 2.0f, &index, &score) ==
+        GUESSTURE_STATUS_OK);
+  CHECK(strcmp(guessture_templates_name(templates, index), "zigzag") == 0);
+  CHECK(score > 0.9f);
+
+  /* A cleared path can be reused, and is too short to match until points are added. */
+  CHECK(guessture_path_clear(path) == GUESSTURE_STATUS_OK);
+  CHECK(guessture_find_matching_template(templates, path, 45.0f, 2.0f, &index, &score) ==
+        GUESSTURE_STATUS_TOO_SHORT);
+  CHECK(guessture_templates_add(templates, "empty", path) == GUESSTURE_STATUS_EMPTY_PATH);
+  guessture_path_free(path);
+
+  CHECK(guessture_find_matching_template(NULL, NULL, 45.0f, 2.0f, &index, &score) ==
+        GUESSTURE_STATUS_NULL_POINTER);
+  CHECK(guessture_templates_name(templates, 2) == NULL);
+  CHECK(strcmp(guessture_status_message(GUESSTURE_STATUS_NO_MATCH),
+               "no template matched the path") == 0);
+  guessture_templates_free(templates);
+
+  /* Templates loaded from `.gestures` JSON keep their names and normalized points, and must
+     have the number of points the file declares. */
+  GuesstureTemplates *loaded = NULL;
+  CHECK(guessture_templates_load_json("not json", &loaded) == GUESSTURE_STATUS_INVALID_JSON);
+  CHECK(guessture_templates_load_json("{\"templates\":[{\"name\":\"dot\",\"path\":[[0,0]]}]}",
+                                      &loaded) == GUESSTURE_STATUS_INVALID_JSON);
+  CHECK(loaded == NULL);
+  char json[1024] = "{\"version\":1,\"num_points\":64,\"templates\":[{\"name\":\"dot\",\"path\":[";
+  for (int i = 0; i < 64; i++) {
+    strcat(json, i == 0 ? "[0,0]" : ",[0,0]");
+  }
+  strcat(json, "]}]}");
+  CHECK(guessture_templates_load_json(json, &loaded) == GUESSTURE_STATUS_OK);
+  CHECK(guessture_templates_len(loaded) == 1);
+  CHECK(strcmp(guessture_templates_name(loaded, 0), "dot") == 0);
+  guessture_templates_free(loaded);
+
+  guessture_path_free(NULL);
+  guessture_templates_free(NULL);
+  return 0;
+}
@@ -0,0 +1,78 @@
+//! Compiles `tests/c_api.c` against the checked-in header and the static library, and runs
+//! it, so that the header and the exported symbols can't drift apart unnoticed.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn crate_dir() -> &'static Path {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+}
+
+/// The directory that cargo places the library in; integration tests run from its `deps`
+/// subdirectory.
+fn target_dir() -> PathBuf {
+    let exe = env::current_exe().unwrap();
+    exe.parent().unwrap().parent().unwrap().to_owned()
+}
+
+/// Build the static library. `cargo test` only builds the rlib that the Rust tests link
+/// against, which would leave the C program linked against an outdated library.
+fn build_static_library(target: &Path) -> PathBuf {
+    let status = Command::new(env!("CARGO"))
+        .args(["build", "--lib", "--manifest-path"])
+        .arg(crate_dir().join("Cargo.toml"))
+        .arg("--target-dir")
+        .arg(target.parent().unwrap())
+        .status()
+        .expect("failed to run cargo");
+    assert!(status.success(), "building the static library failed");
+    target.join("libguessture_ffi.a")
+}
+
+#[test]
+fn header_declares_every_export() {
+    let header = fs::read_to_string(crate_dir().join("include/guessture.h")).unwrap();
+    let source = fs::read_to_string(crate_dir().join("src/lib.rs")).unwrap();
+    let exports: Vec<&str> = source
+        .split("extern \"C\" fn ")
+        .skip(1)
+        .map(|rest| rest.split('(').next().unwrap())
+        .collect();
+    assert!(!exports.is_empty());
+    for name in exports {
+        assert!(header.contains(&format!("{name}(")), "{name} is missing from the header");
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn c_program() {
+    let target = target_dir();
+    let library = build_static_library(&target);
+
+    let program = target.join("c_api");
+    let compiler = env::var("CC").unwrap_or_else(|_| "cc".to_owned());
+    let status = Command::new(compiler)
+        .arg(crate_dir().join("tests/c_api.c"))
+        .arg("-std=c99")
+        .arg("-Wall")
+        .arg("-Werror")
+        .arg("-I")
+        .arg(crate_dir().join("include"))
+        .arg(&library)
+        .args(["-lpthread", "-ldl", "-lm"])
+        .arg("-o")
+        .arg(&program)
+        .status()
+        .expect("failed to run the C compiler");
+    assert!(status.success(), "compiling tests/c_api.c failed");
+
+    let output = Command::new(&program).output().unwrap();
+    assert!(
+        output.status.success(),
+        "C test program failed:\n{}",
+        String::from_utf8_lossy(&output.stderr),
+    );
+}
@@ -0,0 +1,340 @@
+//! Python bindings for `guessture`, built as the `guessture_py` extension module with
+//! maturin. Points are accepted from any iterable of `(x, y)` pairs, including numpy arrays
+//! of shape `(n, 2)`, and returned as lists of tuples so that `numpy.array` can convert them.
+
+use guessture::format::GestureFile;
+use guessture::{find_matching_templates, Error, Invariance, Path2D, Template};
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyIndexError, PyValueError};
+use pyo3::prelude::*;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+create_exception!(
+    guessture_py,
+    MatchError,
+    PyException,
+    "Raised when a path can't be matched against a set of templates."
+);
+
+/// Wraps [Error] so that it can be converted into a Python exception.
+struct PyMatchError(Error);
+
+impl From<PyMatchError> for PyErr {
+    fn from(error: PyMatchError) -> PyErr {
+        let message = match error.0 {
+            Error::TooShort => "the path is too short to match",
+            Error::NoMatch => "no template matched the path",
+        };
+        MatchError::new_err(message)
+    }
+}
+
+/// Read points from an iterable of `(x, y)` pairs.
+fn extract_points(points: &Bound<'_, PyAny>) -> PyResult<Vec<(f32, f32)>> {
+    points
+        .try_iter()?
+        .map(|point| {
+            let coords: Vec<f32> =
+                point?.try_iter()?.map(|c| c?.extract()).collect::<PyResult<_>>()?;
+            match coords[..] {
+                [x, y] => Ok((x, y)),
+                _ => Err(PyValueError::new_err("points must be (x, y) pairs")),
+            }
+        })
+        .collect()
+}
+
+/// Read a path from either a [PyPath] or an iterable of `(x, y)` pairs.
+fn extract_path(path: &Bound<'_, PyAny>) -> PyResult<Path2D> {
+    if let Ok(path) = path.downcast::<PyPath>() {
+        return Ok(path.borrow().0.clone());
+    }
+    let mut result = Path2D::default();
+    for (x, y) in extract_points(path)? {
+        result.push(x, y);
+    }
+    Ok(result)
+}
+
+/// `Template` isn't `Clone`, but Python needs its own copies of templates in a set.
+fn copy_template(template: &Template) -> Template {
+    Template::new_from_template(template.name.clone(), template.path.clone())
+        .expect("templates are never empty")
+        .with_invariance(template.invariance)
+}
+
+/// A path of points recorded from user input.
+#[pyclass(name = "Path")]
+#[derive(Clone, Default)]
+pub struct PyPath(Path2D);
+
+#[pymethods]
+impl PyPath {
+    #[new]
+    #[pyo3(signature = (points=None))]
+    fn new(points: Option<&Bound<'_, PyAny>>) -> PyResult<PyPath> {
+        Ok(PyPath(points.map(extract_path).transpose()?.unwrap_or_default()))
+    }
+
+    /// Add a new point to the end of this path.
+    fn push(&mut self, x: f32, y: f32) {
+        self.0.push(x, y);
+    }
+
+    /// Returns true if the point is different than the last point in this path.
+    fn is_new_point(&self, x: f32, y: f32) -> bool {
+        self.0.is_new_point(x, y)
+    }
+
+    /// Returns the points of this path as a list of `(x, y)` tuples.
+    fn points(&self) -> Vec<(f32, f32)> {
+        self.0.points()
+    }
+
+    fn __len__(&self) -> usize {
+        self.0.points().len()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Path(<{} points>)", self.__len__())
+    }
+}
+
+/// A normalized gesture template.
+#[pyclass(name = "Template")]
+pub struct PyTemplate(Template);
+
+#[pymethods]
+impl PyTemplate {
+    /// Create a normalized template from a path or an iterable of `(x, y)` pairs.
+    #[new]
+    fn new(name: String, path: &Bound<'_, PyAny>) -> PyResult<PyTemplate> {
+        Template::new(name, &extract_path(path)?)
+            .map(PyTemplate)
+            .map_err(|_| PyValueError::new_err("the path is empty"))
+    }
+
+    #[getter]
+    fn name(&self) -> String {
+        self.0.name.clone()
+    }
+
+    /// Returns the normalized points of this template as a list of `(x, y)` tuples.
+    fn points(&self) -> Vec<(f32, f32)> {
+        self.0.path.points()
+    }
+
+    /// The variations of a path that match this template, as a dict of flags.
+    #[getter]
+    fn invariance(&self) -> HashMap<&'static str, bool> {
+        let invariance = self.0.invariance;
+        HashMap::from([
+            ("direction", invariance.direction),
+            ("start_point", invariance.start_point),
+            ("mirror_horizontal", invariance.mirror_horizontal),
+            ("mirror_vertical", invariance.mirror_vertical),
+        ])
+    }
+
+    /// Returns a copy of this template that matches paths with the given variations.
+    #[pyo3(signature = (
+        *, direction=false, start_point=false, mirror_horizontal=false, mirror_vertical=false
+    ))]
+    fn with_invariance(
+        &self,
+        direction: bool,
+        start_point: bool,
+        mirror_horizontal: bool,
+        mirror_vertical: bool,
+    ) -> PyTemplate {
+        PyTemplate(copy_template(&self.0).with_invariance(Invariance {
+            direction,
+            start_point,
+            mirror_horizontal,
+            mirror_vertical,
+        }))
+    }
+
+    /// Returns how closely this template matches another, between 0.0 and 1.0.
+    #[pyo3(signature = (other, angle_range=45.0, angle_precision=2.0))]
+    fn similarity(&self, other: &PyTemplate, angle_range: f32, angle_precision: f32) -> f32 {
+        self.0.similarity(&other.0, angle_range, angle_precision)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Template({:?})", self.0.name)
+    }
+}
+
+/// An ordered set of templates, as stored in a `.gestures` file.
+#[pyclass(name = "Templates")]
+#[derive(Default)]
+pub struct PyTemplates(Vec<Template>);
+
+#[pymethods]
+impl PyTemplates {
+    #[new]
+    #[pyo3(signature = (templates=None))]
+    fn new(templates: Option<Vec<PyRef<'_, PyTemplate>>>) -> PyTemplates {
+        let templates = templates.unwrap_or_default();
+        PyTemplates(templates.iter().map(|t| copy_template(&t.0)).collect())
+    }
+
+    /// Parse the contents of a `.gestures` file, validating it and migrating it to the
+    /// current schema version.
+    #[staticmethod]
+    fn from_json(json: &str) -> PyResult<PyTemplates> {
+        let templates = GestureFile::from_json(json)
+            .and_then(|file| file.to_templates())
+            .map_err(|e| PyValueError::new_err(e.to_string()))?;
+        Ok(PyTemplates(templates))
+    }
+
+    /// Returns the contents of a `.gestures` file containing these templates.
+    fn to_json(&self) -> PyResult<String> {
+        GestureFile::from_templates(&self.0)
+            .to_json()
+            .map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+
+    /// Read a `.gestures` file.
+    #[staticmethod]
+    fn load(filename: PathBuf) -> PyResult<PyTemplates> {
+        PyTemplates::from_json(&fs::read_to_string(filename)?)
+    }
+
+    /// Write these templates to a `.gestures` file.
+    fn save(&self, filename: PathBuf) -> PyResult<()> {
+        fs::write(filename, self.to_json()?)?;
+        Ok(())
+    }
+
+    /// Add a copy of a template to the end of this set.
+    fn append(&mut self, template: &PyTemplate) {
+        self.0.push(copy_template(&template.0));
+    }
+
+    /// Returns the names of the templates, in order.
+    fn names(&self) -> Vec<String> {
+        self.0.iter().map(|t| t.name.clone()).collect()
+    }
+
+    fn __len__(&self) -> usize {
+        self.0.len()
+    }
+
+    fn __getitem__(&self, index: usize) -> PyResult<PyTemplate> {
+        let template = self.0.get(index).ok_or_else(|| PyIndexError::new_err(index))?;
+        Ok(PyTemplate(copy_template(template)))
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Templates(<{} templates>)", self.0.len())
+    }
+}
+
+/// The result of matching a path against a template.
+#[pyclass(name = "Match", frozen, get_all)]
+pub struct PyMatch {
+    /// The name of the matching template.
+    name: String,
+    /// The position of the matching template in its set.
+    index: usize,
+    /// How closely the path matches the template, between 0.0 and 1.0.
+    score: f32,
+}
+
+#[pymethods]
+impl PyMatch {
+    fn __repr__(&self) -> String {
+        format!("Match(name={:?}, index={}, score={})", self.name, self.index, self.score)
+    }
+}
+
+/// Returns the template that most closely matches `path`. `angle_range` and
+/// `angle_precision` are in degrees. Raises `MatchError` if no template matches.
+#[pyfunction(name = "find_matching_template")]
+#[pyo3(signature = (templates, path, angle_range=45.0, angle_precision=2.0))]
+fn py_find_matching_template(
+    templates: &PyTemplates,
+    path: &Bound<'_, PyAny>,
+    angle_range: f32,
+    angle_precision: f32,
+) -> PyResult<PyMatch> {
+    let mut matches = py_find_matching_templates(templates, path, 1, angle_range, angle_precision)?;
+    matches.pop().ok_or_else(|| PyMatchError(Error::NoMatch).into())
+}
+
+/// Returns the `n` templates that most closely match `path`, best first. Raises
+/// `MatchError` if the path is too short to match.
+#[pyfunction(name = "find_matching_templates")]
+#[pyo3(signature = (templates, path, n, angle_range=45.0, angle_precision=2.0))]
+fn py_find_matching_templates(
+    templates: &PyTemplates,
+    path: &Bound<'_, PyAny>,
+    n: usize,
+    angle_range: f32,
+    angle_precision: f32,
+) -> PyResult<Vec<PyMatch>> {
+    let path = extract_path(path)?;
+    let ranked = find_matching_templates(&templates.0, &path, angle_range, angle_precision, n);
+    let ranked = match ranked {
+        Err(Error::NoMatch) => vec![],
+        result => result.map_err(PyMatchError)?,
+    };
+    Ok(ranked
+        .into_iter()
+        .map(|(index, score)| PyMatch {
+            name: templates.0[index].name.clone(),
+            index,
+            score,
+        })
+        .collect())
+}
+
+#[pymodule]
+fn guessture_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyPath>()?;
+    m.add_class::<PyTemplate>()?;
+    m.add_class::<PyTemplates>()?;
+    m.add_class::<PyMatch>()?;
+    m.add_function(wrap_pyfunction!(py_find_matching_template, m)?)?;
+    m.add_function(wrap_pyfunction!(py_find_matching_templates, m)?)?;
+    m.add("MatchError", m.py().get_type::<MatchError>())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(points: &[(f32, f32)]) -> Path2D {
+        let mut path = Path2D::default();
+        for &(x, y) in points {
+            path.push(x, y);
+        }
+        path
+    }
+
+    #[test]
+    fn rank_best_first() {
+        let caret = path(&[(0., 100.), (50., 0.), (100., 100.)]);
+        let vee = path(&[(0., 0.), (50., 100.), (100., 0.)]);
+        let templates = [
+            Template::new("caret".to_owned(), &caret).unwrap(),
+            Template::new("vee".to_owned(), &vee).unwrap(),
+            Template::new("vee".to_owned(), &vee).unwrap(),
+        ];
+        let drawn = path(&[(10., 10.), (60., 105.), (110., 12.)]);
+        let ranked = find_matching_templates(&templates, &drawn, 45.0, 2.0, 3).unwrap();
+        assert_eq!(ranked.iter().map(|r| r.0).collect::<Vec<_>>(), [1, 2, 0]);
+        assert_eq!(ranked[0].1, ranked[1].1);
+        assert!(ranked[0].1 > ranked[2].1);
+
+        let short = path(&[(0., 0.), (1., 1.)]);
+        let result = find_matching_templates(&templates, &short, 45.0, 2.0, 3);
+        assert!(matches!(result, Err(Error::TooShort)));
+    }
+}
@@ -0,0 +1,61 @@
+"""Tests for the Python module. Build it into the current environment with
+`maturin develop` and run `pytest tests`."""
+
+import json
+
+import pytest
+
+import guessture_py as guessture
+
+
+def zigzag(x, y, size):
+    return [(x + size * i, y + (size if i % 2 else 0)) for i in range(5)]
+
+
+def templates():
+    return guessture.Templates([
+        guessture.Template("vee", [(0, 0), (50, 100), (100, 0)]),
+        guessture.Template("zigzag", guessture.Path(zigzag(0, 0, 50))),
+    ])
+
+
+def test_match_and_n_best():
+    path = guessture.Path()
+    for x, y in zigzag(300, -20, 30):
+        path.push(x, y)
+    assert len(path) == 5
+
+    best = guessture.find_matching_template(templates(), path)
+    assert (best.name, best.index) == ("zigzag", 1)
+    assert best.score > 0.9
+
+    ranked = guessture.find_matching_templates(templates(), path, n=5)
+    assert [m.name for m in ranked] == ["zigzag", "vee"]
+    assert ranked[0].score >= ranked[1].score
+
+    with pytest.raises(guessture.MatchError):
+        guessture.find_matching_template(templates(), [(0, 0), (1, 1)])
+
+
+def test_numpy_points():
+    np = pytest.importorskip("numpy")
+    points = np.array(zigzag(0, 0, 30), dtype=np.float32)
+    best = guessture.find_matching_template(templates(), points)
+    assert best.name == "zigzag"
+    assert np.array(templates()[0].points()).shape == (64, 2)
+
+
+def test_save_and_load(tmp_path):
+    original = templates()
+    original.append(original[0].with_invariance(direction=True))
+    filename = tmp_path / "data.gestures"
+    original.save(filename)
+
+    loaded = guessture.Templates.load(filename)
+    assert loaded.names() == ["vee", "zigzag", "vee"]
+    assert loaded[2].invariance["direction"]
+    assert loaded[0].points() == original[0].points()
+    assert json.loads(loaded.to_json()) == json.loads(original.to_json())
+
+    with pytest.raises(ValueError):
+        guessture.Templates.from_json("{")